@@ -0,0 +1,27 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var texture_sampler: sampler;
+
+struct DamageVignette {
+    amount: f32,
+    time: f32,
+    reach: f32,
+    _webgl2_padding: f32,
+}
+
+@group(0) @binding(2) var<uniform> settings: DamageVignette;
+
+const BLOOD: vec3<f32> = vec3<f32>(0.55, 0.02, 0.02);
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(screen_texture, texture_sampler, in.uv).rgb;
+
+    // heartbeat
+    let pulse = 0.85 + 0.15 * sin(settings.time * 8.0);
+    let dist = length(in.uv - vec2<f32>(0.5)) * 1.41421356;
+    let edge = smoothstep(1.0 - settings.reach, 1.0, dist) * settings.amount * pulse;
+
+    return vec4<f32>(mix(color, BLOOD, clamp(edge, 0.0, 1.0)), 1.0);
+}
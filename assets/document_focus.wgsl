@@ -0,0 +1,21 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var texture_sampler: sampler;
+
+struct DocumentFocus {
+    amount: f32,
+    saturation: f32,
+    brightness: f32,
+    _webgl2_padding: f32,
+}
+
+@group(0) @binding(2) var<uniform> settings: DocumentFocus;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(screen_texture, texture_sampler, in.uv).rgb;
+    let gray = vec3<f32>(dot(color, vec3<f32>(0.299, 0.587, 0.114)));
+    let focused = mix(gray, color, settings.saturation) * settings.brightness;
+    return vec4<f32>(mix(color, focused, settings.amount), 1.0);
+}
@@ -12,9 +12,15 @@ struct K {
     peak_intensity: f32,
     wave_frequency: f32,
     wave_intensity: f32,
-#ifdef SIXTEEN_BYTE_ALIGNMENT
-    _webgl2_padding: vec2<f32>,
-#endif
+    posterize: f32,
+    hue_shift: f32,
+    grain: f32,
+    spiral_time: f32,
+    effects: u32,
+    audio_rms: f32,
+    audio_low: f32,
+    // negative unless safe mode limits it, keep in sync with K in k.rs
+    safe_intensity: f32,
 }
 
 @group(0) @binding(2) var<uniform> settings: K;
@@ -22,6 +28,21 @@ struct K {
 const PI: f32 = 3.14159265359;
 const TAU: f32 = 6.28318530718;
 
+// KEffects in k.rs
+const WAVE: u32 = 1u;
+const STEREOGRAPHIC: u32 = 2u;
+const HOLE: u32 = 4u;
+const KALEIDOSCOPE: u32 = 8u;
+const CHROMATIC_ABERRATION: u32 = 16u;
+const COLOR_SHIFT: u32 = 32u;
+const FBM: u32 = 64u;
+const VIGNETTE: u32 = 128u;
+const GRAIN: u32 = 256u;
+
+fn enabled(effect: u32) -> bool {
+    return (settings.effects & effect) != 0u;
+}
+
 fn hash(p: vec2<f32>) -> f32 {
     let h = dot(p, vec2<f32>(127.1, 311.7));
     return fract(sin(h) * 43758.5453123);
@@ -50,6 +71,7 @@ fn fbm(p: vec2<f32>) -> f32 {
     return value;
 }
 
+// ported to K::dynamic_intensity in k.rs, keep the two in sync
 fn dynamic_intensity(time: f32) -> f32 {
     let base = settings.base_level;
     let peak_freq = settings.peak_frequency;
@@ -91,7 +113,8 @@ fn stereographic_distort(uv: vec2<f32>, time: f32, intensity: f32) -> vec2<f32>
     
     let projected_r = tan(r * PI * 0.4 * intensity) * 0.3;
     let spiral = theta + r * 3.0 * sin(time * 0.3) * intensity;
-    let breath = 1.0 + 0.1 * sin(time * 0.5) * intensity;
+    // the bass pushes the breathing out
+    let breath = 1.0 + (0.1 * sin(time * 0.5) + 0.3 * settings.audio_low) * intensity;
     
     return center + vec2<f32>(cos(spiral), sin(spiral)) * projected_r * breath;
 }
@@ -146,7 +169,7 @@ fn chromatic_aberration(uv: vec2<f32>, intensity: f32) -> vec3<f32> {
 }
 
 fn shader_color_shift(color: vec3<f32>, time: f32, intensity: f32) -> vec3<f32> {
-    let angle = time * 0.3 * intensity;
+    let angle = time * 0.3 * intensity * settings.hue_shift;
     let s = sin(angle);
     let c = cos(angle);
     let weights = vec3<f32>(0.213, 0.715, 0.072);
@@ -170,12 +193,12 @@ fn shader_color_shift(color: vec3<f32>, time: f32, intensity: f32) -> vec3<f32>
     
     // Pulsing saturation
     let gray = dot(result, vec3<f32>(0.299, 0.587, 0.114));
-    let sat_pulse = 1.0 + 0.5 * sin(time * 2.0) * intensity;
+    let sat_pulse = 1.0 + 0.5 * sin(time * 2.0) * intensity * settings.hue_shift;
     result = mix(vec3<f32>(gray), result, sat_pulse);
     
     // Posterization
     let posterize = 4.0 + 12.0 * (1.0 - intensity);
-    result = floor(result * posterize) / posterize;
+    result = mix(result, floor(result * posterize) / posterize, settings.posterize);
     
     return result;
 }
@@ -184,9 +207,15 @@ fn shader_color_shift(color: vec3<f32>, time: f32, intensity: f32) -> vec3<f32>
 fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
     var uv = in.uv;
     let time = settings.time;
+    // the spirals run on their own clock so safe mode can slow them down
+    let spiral_time = settings.spiral_time;
     let master = settings.intensity;
     
-    let shader_intensity = dynamic_intensity(time) * master;
+    // loud moments push the trip a little further
+    var shader_intensity = clamp(dynamic_intensity(time) * master * (1.0 + 0.3 * settings.audio_rms), 0.0, 1.0);
+    if settings.safe_intensity >= 0.0 {
+        shader_intensity = settings.safe_intensity;
+    }
     
     // thresholds
     let wave_active = smoothstep(0.15, 0.35, shader_intensity);
@@ -194,42 +223,56 @@ fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
     let kaleido_active = smoothstep(0.45, 0.65, shader_intensity);
     let khole_active = smoothstep(0.65, 0.85, shader_intensity);
     
-    if (wave_active > 0.01) {
+    if (enabled(WAVE) && wave_active > 0.01) {
         uv = wave_distort(uv, time, wave_active * 0.8);
     }
     
-    if (stereo_active > 0.01) {
-        uv = mix(uv, stereographic_distort(uv, time, stereo_active), stereo_active * 0.5);
+    if (enabled(STEREOGRAPHIC) && stereo_active > 0.01) {
+        uv = mix(uv, stereographic_distort(uv, spiral_time, stereo_active), stereo_active * 0.5);
     }
     
-    if (khole_active > 0.01) {
-        uv = hole(uv, time, khole_active * 0.3);
+    if (enabled(HOLE) && khole_active > 0.01) {
+        uv = hole(uv, spiral_time, khole_active * 0.3);
     }
     
-    if (kaleido_active > 0.01) {
-        let segments = 6.0 + 2.0 * sin(time * 0.2);
-        let kaleido_uv = kaleidoscope(uv, segments, time);
+    if (enabled(KALEIDOSCOPE) && kaleido_active > 0.01) {
+        let segments = 6.0 + 2.0 * sin(spiral_time * 0.2);
+        let kaleido_uv = kaleidoscope(uv, segments, spiral_time);
         uv = mix(uv, kaleido_uv, kaleido_active * 0.4);
     }
     
     uv = clamp(uv, vec2<f32>(0.001), vec2<f32>(0.999));
     
-    var color = chromatic_aberration(uv, shader_intensity * 2.0);
-    color = shader_color_shift(color, time, shader_intensity);
+    var color: vec3<f32>;
+    if (enabled(CHROMATIC_ABERRATION)) {
+        color = chromatic_aberration(uv, shader_intensity * 2.0);
+    } else {
+        color = textureSample(screen_texture, texture_sampler, uv).rgb;
+    }
+    
+    if (enabled(COLOR_SHIFT)) {
+        color = shader_color_shift(color, time, shader_intensity);
+    }
     
     // Fractal noise overlay
-    let fractal = fbm(uv * 3.0 + vec2<f32>(time * 0.1, time * 0.05));
-    color += (fractal - 0.5) * shader_intensity * 0.15;
+    if (enabled(FBM)) {
+        let fractal = fbm(uv * 3.0 + vec2<f32>(time * 0.1, time * 0.05));
+        color += (fractal - 0.5) * shader_intensity * 0.15;
+    }
     
     // vignette
-    let dist_from_center = length(uv - vec2<f32>(0.5));
-    let vignette_pulse = 1.0 + 0.1 * sin(time * 0.7);
-    let vignette = 1.0 - smoothstep(0.3, 0.8 * vignette_pulse, dist_from_center) * (0.3 + shader_intensity * 0.4);
-    color *= vignette;
+    if (enabled(VIGNETTE)) {
+        let dist_from_center = length(uv - vec2<f32>(0.5));
+        let vignette_pulse = 1.0 + 0.1 * sin(time * 0.7);
+        let vignette = 1.0 - smoothstep(0.3, 0.8 * vignette_pulse, dist_from_center) * (0.3 + shader_intensity * 0.4);
+        color *= vignette;
+    }
     
     // grain
-    let grain = (hash(uv * 100.0 + time * 100.0) - 0.5) * 0.05 * shader_intensity;
-    color += grain;
+    if (enabled(GRAIN)) {
+        let grain = (hash(uv * 100.0 + time * 100.0) - 0.5) * 0.05 * shader_intensity * settings.grain;
+        color += grain;
+    }
     
     return vec4<f32>(clamp(color, vec3<f32>(0.0), vec3<f32>(1.5)), 1.0);
 }
@@ -0,0 +1,45 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var texture_sampler: sampler;
+
+struct Vhs {
+    intensity: f32,
+    time: f32,
+    scanlines: f32,
+    noise: f32,
+}
+
+@group(0) @binding(2) var<uniform> settings: Vhs;
+
+fn hash(p: vec2<f32>) -> f32 {
+    let h = dot(p, vec2<f32>(127.1, 311.7));
+    return fract(sin(h) * 43758.5453123);
+}
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let intensity = settings.intensity;
+    let time = settings.time;
+    var uv = in.uv;
+
+    // tracking: rows drift sideways, worst in a band rolling down the screen
+    let band = smoothstep(0.9, 1.0, sin(uv.y * 3.0 - time * 0.7));
+    let row = floor(uv.y * settings.scanlines);
+    uv.x += (hash(vec2<f32>(row, floor(time * 30.0))) - 0.5) * 0.01 * (1.0 + 6.0 * band) * intensity;
+
+    // chroma bleeds to the right
+    let bleed = 0.004 * intensity;
+    let r = textureSample(screen_texture, texture_sampler, uv + vec2<f32>(bleed, 0.0)).r;
+    let g = textureSample(screen_texture, texture_sampler, uv).g;
+    let b = textureSample(screen_texture, texture_sampler, uv - vec2<f32>(bleed, 0.0)).b;
+    var color = vec3<f32>(r, g, b);
+
+    let scanline = 0.5 + 0.5 * cos(in.uv.y * settings.scanlines * 6.28318530718);
+    color *= 1.0 - 0.25 * scanline * intensity;
+
+    let snow = hash(in.uv * 400.0 + time * 60.0) - 0.5;
+    color += snow * settings.noise * 0.15 * intensity;
+
+    return vec4<f32>(color, 1.0);
+}
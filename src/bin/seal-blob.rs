@@ -0,0 +1,57 @@
+//! Seals a document so it only opens once the given documents are collected.
+//!
+//! `cargo run --bin seal-blob -- <input> <output.blob> <required.blob>...`
+//!
+//! The names of the required documents are stored in the sealed blob, the game unseals it
+//! as soon as the player has collected all of them, see [`stoned::seal`].
+
+use std::{path::Path, process::ExitCode};
+
+use stoned::seal::{SealKey, seal};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [input, output, required @ ..] = args.as_slice() else {
+        eprintln!("usage: seal-blob <input> <output.blob> <required.blob>...");
+        return ExitCode::FAILURE;
+    };
+    if required.is_empty() {
+        eprintln!("at least one required document is needed to derive a key");
+        return ExitCode::FAILURE;
+    }
+
+    match run(input, output, required) {
+        Ok(()) => {
+            println!("sealed {input} into {output}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(input: &str, output: &str, required: &[String]) -> Result<(), String> {
+    let read = |path: &str| std::fs::read(path).map_err(|e| format!("{path}: {e}"));
+    let plaintext = read(input)?;
+    let documents = required
+        .iter()
+        .map(|path| Ok((blob_name(path), read(path)?)))
+        .collect::<Result<Vec<_>, String>>()?;
+    let key = SealKey::derive(
+        documents
+            .iter()
+            .map(|(name, bytes)| (name.as_str(), bytes.as_slice())),
+    );
+    let requires = documents.iter().map(|(name, _)| name.as_str());
+    let sealed = seal(&plaintext, requires, &key).map_err(|e| e.to_string())?;
+    std::fs::write(output, sealed).map_err(|e| format!("{output}: {e}"))
+}
+
+/// Same name the game derives the key with, see `Blob::file_name`
+fn blob_name(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map_or_else(|| path.to_owned(), |n| n.to_string_lossy().into_owned())
+}
@@ -0,0 +1,278 @@
+//! Checks the Skein components authored in the level against the assets folder.
+//!
+//! `cargo run --bin validate-assets [path/to/level.glb]`
+//!
+//! Every [`File`], [`DynamicProp`], [`StaticProp`] and [`InspectInfo`] is deserialized
+//! through reflection, referenced paths have to exist with a supported extension,
+//! and every [`Dialogues`] variant has to be used by at least one file.
+//! [`SurfaceMaterial`]s are deserialized too, and each surface needs its footstep samples.
+
+use std::{
+    any::TypeId,
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use bevy::reflect::{
+    Enum, FromReflect, PartialReflect, ReflectRef, TypeRegistry, Typed,
+    serde::TypedReflectDeserializer,
+};
+use serde::de::DeserializeSeed;
+use serde_json::Value;
+use stoned::{
+    DynamicProp, File, InspectInfo, LEVEL, StaticProp, dialogue::Dialogues,
+    footsteps::SurfaceMaterial,
+};
+
+const ASSETS: &str = "assets";
+const BLOB_EXTENSIONS: &[&str] = &["blob"];
+const SOUND_EXTENSIONS: &[&str] = &["ogg", "mp3", "wav", "flac"];
+/// gltf objects that Skein can attach components to
+const SKEIN_TARGETS: &[&str] = &["nodes", "meshes", "materials", "scenes"];
+
+fn main() -> ExitCode {
+    let level = std::env::args()
+        .nth(1)
+        .map_or_else(|| Path::new(ASSETS).join(LEVEL), PathBuf::from);
+
+    let mut problems = match validate(&level) {
+        Ok(problems) => problems,
+        Err(e) => vec![format!("{}: {e}", level.display())],
+    };
+    check_footsteps(&mut problems);
+
+    if problems.is_empty() {
+        println!("{}: ok", level.display());
+        return ExitCode::SUCCESS;
+    }
+    for problem in &problems {
+        eprintln!("error: {problem}");
+    }
+    eprintln!("{} problem(s) in {}", problems.len(), level.display());
+    ExitCode::FAILURE
+}
+
+fn validate(level: &Path) -> Result<Vec<String>, String> {
+    let bytes = std::fs::read(level).map_err(|e| e.to_string())?;
+    Ok(check_level(&gltf_json(&bytes)?))
+}
+
+/// Checks the Skein components in the json of a gltf
+fn check_level(gltf: &Value) -> Vec<String> {
+    let mut registry = TypeRegistry::new();
+    registry.register::<File>();
+    registry.register::<DynamicProp>();
+    registry.register::<StaticProp>();
+    registry.register::<InspectInfo>();
+    registry.register::<SurfaceMaterial>();
+
+    let mut problems = Vec::new();
+    let mut dialogues = BTreeSet::new();
+
+    for target in SKEIN_TARGETS {
+        let Some(objects) = gltf.get(*target).and_then(Value::as_array) else {
+            continue;
+        };
+        for (index, object) in objects.iter().enumerate() {
+            let name = match object.get("name").and_then(Value::as_str) {
+                Some(name) => format!("{target}[{index}] \"{name}\""),
+                None => format!("{target}[{index}]"),
+            };
+            let Some(components) = object.pointer("/extras/skein").and_then(Value::as_array) else {
+                continue;
+            };
+            for (type_path, value) in components.iter().filter_map(Value::as_object).flatten() {
+                let Some(registration) = registry.get_with_type_path(type_path) else {
+                    continue;
+                };
+                let reflected = match TypedReflectDeserializer::new(registration, &registry)
+                    .deserialize(value)
+                {
+                    Ok(reflected) => reflected,
+                    Err(e) => {
+                        problems.push(format!("{name}: invalid {type_path}: {e}"));
+                        continue;
+                    }
+                };
+
+                let type_id = registration.type_id();
+                if type_id == TypeId::of::<File>() {
+                    let Some(file) = File::from_reflect(&*reflected) else {
+                        problems.push(format!("{name}: incomplete {type_path}"));
+                        continue;
+                    };
+                    check_path(&mut problems, &name, "file", &file.file, BLOB_EXTENSIONS);
+                    check_path(&mut problems, &name, "sound", &file.sound, SOUND_EXTENSIONS);
+                    dialogues.insert(file.dialogue.variant_name().to_owned());
+                } else if type_id == TypeId::of::<DynamicProp>() {
+                    check_density(&mut problems, &name, &*reflected);
+                } else if type_id == TypeId::of::<InspectInfo>() {
+                    let Some(info) = InspectInfo::from_reflect(&*reflected) else {
+                        problems.push(format!("{name}: incomplete {type_path}"));
+                        continue;
+                    };
+                    if info.title.is_empty() && info.description.is_empty() {
+                        problems.push(format!("{name}: InspectInfo has no text"));
+                    }
+                }
+            }
+        }
+    }
+
+    let variants = Dialogues::type_info()
+        .as_enum()
+        .map(|info| info.variant_names())
+        .unwrap_or_default();
+    for variant in variants {
+        if !dialogues.contains(*variant) {
+            problems.push(format!("Dialogues::{variant} is not used by any File"));
+        }
+    }
+
+    problems
+}
+
+/// Reads the json chunk of a `.glb`, or the whole file for a `.gltf`
+fn gltf_json(bytes: &[u8]) -> Result<Value, String> {
+    let json = if bytes.starts_with(b"glTF") {
+        let header = |at: usize| {
+            bytes
+                .get(at..at + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        };
+        let length = header(12).ok_or("truncated glb header")?;
+        if bytes.get(16..20) != Some(&b"JSON"[..]) {
+            return Err("first glb chunk is not json".into());
+        }
+        bytes
+            .get(20..20 + length)
+            .ok_or("truncated glb json chunk")?
+    } else {
+        bytes
+    };
+    serde_json::from_slice(json).map_err(|e| e.to_string())
+}
+
+fn check_path(
+    problems: &mut Vec<String>,
+    name: &str,
+    field: &str,
+    path: &str,
+    extensions: &[&str],
+) {
+    let full = Path::new(ASSETS).join(path);
+    let extension = full
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    if !extensions.contains(&extension) {
+        problems.push(format!(
+            "{name}: File::{field} \"{path}\" should end in one of {extensions:?}"
+        ));
+    }
+    if !full.is_file() {
+        problems.push(format!(
+            "{name}: File::{field} \"{path}\" does not exist in {ASSETS}/"
+        ));
+    }
+}
+
+fn check_footsteps(problems: &mut Vec<String>) {
+    for surface in SurfaceMaterial::ALL {
+        for sample in surface.samples() {
+            if !Path::new(ASSETS).join(&sample).is_file() {
+                problems.push(format!(
+                    "{surface:?} footstep \"{sample}\" does not exist in {ASSETS}/"
+                ));
+            }
+        }
+    }
+}
+
+fn check_density(problems: &mut Vec<String>, name: &str, prop: &dyn PartialReflect) {
+    let ReflectRef::Struct(prop) = prop.reflect_ref() else {
+        return;
+    };
+    let density = prop
+        .field("density")
+        .and_then(|d| d.try_downcast_ref::<f32>())
+        .copied();
+    if density.is_some_and(|d| d <= 0.0) {
+        problems.push(format!("{name}: DynamicProp::density has to be positive"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// A level with a node for every set of Skein components
+    fn level(nodes: impl IntoIterator<Item = Value>) -> Value {
+        let nodes: Vec<_> = nodes
+            .into_iter()
+            .map(|skein| json!({ "name": "node", "extras": { "skein": skein } }))
+            .collect();
+        json!({ "nodes": nodes })
+    }
+
+    fn file(file: &str, dialogue: &str) -> Value {
+        json!([{ "stoned::File": { "file": file, "sound": "711.ogg", "dialogue": dialogue } }])
+    }
+
+    /// A file for every dialogue, all of them shipped
+    fn complete() -> Vec<Value> {
+        Dialogues::type_info()
+            .as_enum()
+            .unwrap()
+            .variant_names()
+            .iter()
+            .map(|dialogue| file("EFTA00028716.pdf.blob", dialogue))
+            .collect()
+    }
+
+    #[test]
+    fn complete_level_passes() {
+        assert_eq!(check_level(&level(complete())), Vec::<String>::new());
+    }
+
+    #[test]
+    fn missing_blob() {
+        let mut nodes = complete();
+        nodes.push(file("EFTA99999999.pdf.blob", "Acid"));
+        let problems = check_level(&level(nodes));
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(problems[0].contains("EFTA99999999.pdf.blob"));
+    }
+
+    #[test]
+    fn wrong_extension() {
+        let mut nodes = complete();
+        nodes.push(file("711.ogg", "Acid"));
+        let problems = check_level(&level(nodes));
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(problems[0].contains("should end in"));
+    }
+
+    #[test]
+    fn unused_dialogue() {
+        let problems = check_level(&level([file("EFTA00028716.pdf.blob", "Acid")]));
+        assert!(
+            problems
+                .iter()
+                .any(|p| p == "Dialogues::Romantic is not used by any File")
+        );
+        assert!(!problems.iter().any(|p| p.contains("Acid")));
+    }
+
+    #[test]
+    fn unknown_surface_material() {
+        let mut nodes = complete();
+        nodes.push(json!([{ "stoned::SurfaceMaterial": "Lava" }]));
+        let problems = check_level(&level(nodes));
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(problems[0].contains("invalid stoned::SurfaceMaterial"));
+    }
+}
@@ -1,18 +1,236 @@
+use std::path::{Path, PathBuf};
+
 use bevy::{
-    asset::{AssetLoader, LoadContext, io::Reader},
+    asset::{AssetLoader, AssetPath, LoadContext, io::Reader},
     prelude::*,
     reflect::TypePath,
 };
 use thiserror::Error;
 
+use crate::{
+    File, Progress,
+    seal::{self, SealError, SealKey},
+};
+
 pub(super) fn plugin(app: &mut App) {
     app.init_asset::<Blob>()
-        .init_asset_loader::<BlobAssetLoader>();
+        .register_asset_reflect::<Blob>()
+        .init_asset_loader::<BlobAssetLoader>()
+        .add_systems(
+            Update,
+            (
+                notify_blob_loaded,
+                unlock_sealed_blobs.run_if(document_collected.or(blob_loaded)),
+            ),
+        )
+        .add_observer(load_file_blob);
+    #[cfg(target_arch = "wasm32")]
+    app.add_observer(download_collected);
 }
 
-#[derive(Asset, TypePath, Debug)]
+/// Any file shipped as `<name>.<ext>.blob`, the inner extension decides the content type
+#[derive(Asset, Reflect, Debug)]
+#[reflect(Debug)]
 pub struct Blob {
     bytes: Vec<u8>,
+    path: AssetPath<'static>,
+}
+
+impl Blob {
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The asset path this blob was loaded from
+    pub fn path(&self) -> &AssetPath<'static> {
+        &self.path
+    }
+
+    /// The file name without the `.blob` suffix, e.g. `EFTA00028716.pdf`
+    pub fn file_name(&self) -> &str {
+        self.path
+            .path()
+            .file_stem()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default()
+    }
+
+    /// MIME type guessed from the inner extension
+    pub fn content_type(&self) -> &'static str {
+        content_type(Path::new(self.file_name()))
+    }
+
+    /// Sealed blobs stay encrypted until [`Blob::unseal`] succeeds, see [`seal`]
+    pub fn is_sealed(&self) -> bool {
+        seal::is_sealed(&self.bytes)
+    }
+
+    /// Names of the documents a sealed blob needs to open, empty if it isn't sealed
+    pub fn requires(&self) -> Vec<&str> {
+        seal::requires(&self.bytes).unwrap_or_default()
+    }
+
+    /// Decrypts the blob in place, a failed attempt leaves it untouched
+    pub fn unseal(&mut self, key: &SealKey) -> Result<(), SealError> {
+        self.bytes = seal::unseal(&self.bytes, key)?;
+        Ok(())
+    }
+
+    /// Hands the original file to the player.
+    /// On the web this is a browser download, natively it's written to the downloads folder.
+    pub fn download(&self, filename: &str) {
+        #[cfg(target_arch = "wasm32")]
+        trigger_download(self.bytes.clone(), filename, self.content_type());
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let dir = downloads_dir();
+            let path = dir.join(filename);
+            match std::fs::create_dir_all(&dir).and_then(|()| std::fs::write(&path, &self.bytes)) {
+                Ok(()) => info!("saved {}", path.display()),
+                Err(e) => error!("could not save {}: {e}", path.display()),
+            }
+        }
+    }
+}
+
+/// `$XDG_DOWNLOAD_DIR`, or `Downloads` in the home folder
+#[cfg(not(target_arch = "wasm32"))]
+fn downloads_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("XDG_DOWNLOAD_DIR") {
+        return dir.into();
+    }
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map_or_else(
+            || PathBuf::from("."),
+            |home| Path::new(&home).join("Downloads"),
+        )
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("pdf") => "application/pdf",
+        Some("txt") => "text/plain",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        Some("gif") => "image/gif",
+        _ => "application/octet-stream",
+    }
+}
+
+/// The blob behind a [`File`], loaded as soon as the file spawns
+#[derive(Component, Deref)]
+pub struct FileBlob(pub Handle<Blob>);
+
+/// Triggered on a [`File`] once its blob finished loading
+#[derive(EntityEvent)]
+pub struct BlobLoaded {
+    #[event_target]
+    pub file: Entity,
+    pub blob: Handle<Blob>,
+}
+
+fn load_file_blob(
+    on: On<Add, File>,
+    mut cmd: Commands,
+    files: Query<&File>,
+    ass: Res<AssetServer>,
+) {
+    let file = files.get(on.entity).unwrap();
+    cmd.entity(on.entity).insert(FileBlob(ass.load(&file.file)));
+}
+
+/// The web build hands collected files to the browser as well
+#[cfg(target_arch = "wasm32")]
+fn download_collected(
+    on: On<crate::FileCollected>,
+    files: Query<&FileBlob>,
+    blobs: Res<Assets<Blob>>,
+) {
+    let Some(blob) = files.get(on.file).ok().and_then(|b| blobs.get(&b.0)) else {
+        return;
+    };
+    if !blob.is_sealed() {
+        blob.download(blob.file_name());
+    }
+}
+
+fn notify_blob_loaded(
+    mut cmd: Commands,
+    mut events: MessageReader<AssetEvent<Blob>>,
+    files: Query<(Entity, &FileBlob)>,
+) {
+    for event in events.read() {
+        let AssetEvent::LoadedWithDependencies { id } = event else {
+            continue;
+        };
+        for (file, blob) in &files {
+            if blob.id() == *id {
+                cmd.trigger(BlobLoaded {
+                    file,
+                    blob: blob.0.clone(),
+                });
+            }
+        }
+    }
+}
+
+fn document_collected(prog: Res<Progress>, mut seen: Local<usize>) -> bool {
+    let collected = prog.documents.len() != *seen;
+    *seen = prog.documents.len();
+    collected
+}
+
+/// A blob finished loading this frame, it might be the last one a seal was waiting for
+fn blob_loaded(mut events: MessageReader<AssetEvent<Blob>>) -> bool {
+    events
+        .read()
+        .any(|e| matches!(e, AssetEvent::LoadedWithDependencies { .. }))
+}
+
+/// Opens the collected sealed documents once every document they require is collected,
+/// with a key derived from just those documents.
+fn unlock_sealed_blobs(prog: Res<Progress>, mut blobs: ResMut<Assets<Blob>>) {
+    let collected = |name: &str| {
+        prog.documents
+            .iter()
+            .filter_map(|h| blobs.get(h))
+            .find(|b| !b.is_sealed() && b.file_name() == name)
+    };
+
+    let mut keys = Vec::new();
+    for handle in &prog.documents {
+        let Some(blob) = blobs.get(handle).filter(|b| b.is_sealed()) else {
+            continue;
+        };
+        let required: Option<Vec<_>> = blob.requires().into_iter().map(&collected).collect();
+        // expected until everything it needs is collected
+        let Some(required) = required.filter(|r| !r.is_empty()) else {
+            continue;
+        };
+        let key = SealKey::derive(required.iter().map(|b| (b.file_name(), b.bytes())));
+        keys.push((handle.id(), key));
+    }
+
+    for (id, key) in keys {
+        let Some(blob) = blobs.get_mut(id) else {
+            continue;
+        };
+        match blob.unseal(&key) {
+            Ok(()) => info!("unsealed {}", blob.file_name()),
+            Err(e) => error!("could not unseal {}: {e}", blob.file_name()),
+        }
+    }
 }
 
 #[derive(Default, TypePath)]
@@ -41,13 +259,10 @@ impl AssetLoader for BlobAssetLoader {
         info!("Loading Blob...");
         let mut bytes = Vec::new();
         reader.read_to_end(&mut bytes).await?;
-
-        #[cfg(target_arch = "wasm32")]
-        if let Some(name) = ctx.path().path().file_stem() {
-            trigger_download(bytes.clone(), name.to_str().unwrap());
-        };
-
-        Ok(Blob { bytes })
+        Ok(Blob {
+            bytes,
+            path: ctx.path().clone(),
+        })
     }
 
     fn extensions(&self) -> &[&str] {
@@ -55,8 +270,8 @@ impl AssetLoader for BlobAssetLoader {
     }
 }
 
-//#[cfg(target_arch = "wasm32")]
-fn trigger_download(data: Vec<u8>, filename: &str) {
+#[cfg(target_arch = "wasm32")]
+fn trigger_download(data: Vec<u8>, filename: &str, content_type: &str) {
     use js_sys::{Array, Uint8Array};
     use wasm_bindgen::JsCast;
     use web_sys::{Blob, BlobPropertyBag, HtmlAnchorElement, Url};
@@ -66,7 +281,7 @@ fn trigger_download(data: Vec<u8>, filename: &str) {
     parts.push(&uint8_array);
 
     let opts = BlobPropertyBag::new();
-    opts.set_type("application/pdf");
+    opts.set_type(content_type);
     let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &opts).unwrap();
 
     let url = Url::create_object_url_with_blob(&blob).unwrap();
@@ -0,0 +1,901 @@
+//! Rebindable controls. The bindings are part of [`Settings`], every input context
+//! is rebuilt from them when they change.
+
+use std::collections::BTreeMap;
+
+use bevy::{
+    ecs::{relationship::RelatedSpawner, spawn::SpawnWith},
+    prelude::*,
+};
+use bevy_ahoy::prelude::*;
+use bevy_enhanced_input::prelude::{Press, Release, *};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    inspect::Inspect,
+    interact::Interact,
+    k::KEffects,
+    player::PlayerInput,
+    settings::{Graphics, PlayerSettings, Settings},
+    sprint::Sprint,
+    throw::ChargeThrow,
+    ui_input::UiActivate,
+    utils::{DebugInput, Reset, ToggleDebug},
+    widgets::{
+        ControlsList, ControlsStatus, ControlsText, OptionsList, controls_row, controls_screen,
+        option_row,
+    },
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            (apply_controls, restore_mocks, apply_look)
+                .chain()
+                .run_if(resource_changed::<Settings>),
+            capture_input,
+            show_controls,
+        )
+            .chain(),
+    )
+    .init_resource::<HeldMocks>()
+    .add_observer(on_controls_action)
+    .add_observer(toggle_crouch);
+}
+
+/// Everything that can be rebound
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Control {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Crouch,
+    Sprint,
+    PullObject,
+    ThrowObject,
+    Interact,
+    Inspect,
+    Reset,
+    ToggleDebug,
+}
+
+impl Control {
+    pub const ALL: [Control; 13] = [
+        Control::MoveForward,
+        Control::MoveBack,
+        Control::MoveLeft,
+        Control::MoveRight,
+        Control::Jump,
+        Control::Crouch,
+        Control::Sprint,
+        Control::PullObject,
+        Control::ThrowObject,
+        Control::Interact,
+        Control::Inspect,
+        Control::Reset,
+        Control::ToggleDebug,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Control::MoveForward => "move forward",
+            Control::MoveBack => "move back",
+            Control::MoveLeft => "move left",
+            Control::MoveRight => "move right",
+            Control::Jump => "jump",
+            Control::Crouch => "crouch",
+            Control::Sprint => "sprint",
+            Control::PullObject => "pickup object",
+            Control::ThrowObject => "throw object",
+            Control::Interact => "interact",
+            Control::Inspect => "inspect object",
+            Control::Reset => "reset",
+            Control::ToggleDebug => "toggle debug",
+        }
+    }
+
+    fn default_binding(self) -> ControlBinding {
+        let (keyboard, gamepad): (DesktopInput, _) = match self {
+            Control::MoveForward => (KeyCode::KeyW.into(), None),
+            Control::MoveBack => (KeyCode::KeyS.into(), None),
+            Control::MoveLeft => (KeyCode::KeyA.into(), None),
+            Control::MoveRight => (KeyCode::KeyD.into(), None),
+            Control::Jump => (KeyCode::Space.into(), Some(GamepadButton::South)),
+            Control::Crouch => (
+                KeyCode::ControlLeft.into(),
+                Some(GamepadButton::LeftTrigger2),
+            ),
+            Control::Sprint => (KeyCode::ShiftLeft.into(), Some(GamepadButton::LeftThumb)),
+            Control::PullObject => (KeyCode::KeyE.into(), None),
+            Control::ThrowObject => (KeyCode::KeyQ.into(), None),
+            Control::Interact => (KeyCode::KeyF.into(), Some(GamepadButton::West)),
+            Control::Inspect => (MouseButton::Right.into(), Some(GamepadButton::North)),
+            Control::Reset => (KeyCode::KeyR.into(), Some(GamepadButton::Select)),
+            Control::ToggleDebug => (KeyCode::Backquote.into(), Some(GamepadButton::Start)),
+        };
+        ControlBinding {
+            keyboard: Some(keyboard),
+            gamepad,
+        }
+    }
+}
+
+/// Which of a control's two bindings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Slot {
+    Keyboard,
+    Gamepad,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DesktopInput {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+impl DesktopInput {
+    pub fn label(self) -> String {
+        match self {
+            DesktopInput::Key(key) => {
+                let name = format!("{key:?}");
+                ["Key", "Digit"]
+                    .iter()
+                    .find_map(|prefix| name.strip_prefix(prefix))
+                    .unwrap_or(&name)
+                    .to_owned()
+            }
+            DesktopInput::Mouse(button) => format!("Mouse {button:?}"),
+        }
+    }
+}
+
+impl From<KeyCode> for DesktopInput {
+    fn from(key: KeyCode) -> Self {
+        DesktopInput::Key(key)
+    }
+}
+
+impl From<MouseButton> for DesktopInput {
+    fn from(button: MouseButton) -> Self {
+        DesktopInput::Mouse(button)
+    }
+}
+
+impl From<DesktopInput> for Binding {
+    fn from(input: DesktopInput) -> Self {
+        match input {
+            DesktopInput::Key(key) => key.into(),
+            DesktopInput::Mouse(button) => button.into(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct ControlBinding {
+    pub keyboard: Option<DesktopInput>,
+    pub gamepad: Option<GamepadButton>,
+}
+
+impl ControlBinding {
+    fn bindings(self) -> Vec<Binding> {
+        let keyboard = self.keyboard.map(Binding::from);
+        let gamepad = self.gamepad.map(Binding::from);
+        keyboard.into_iter().chain(gamepad).collect()
+    }
+}
+
+/// Controls that aren't in the map keep their default binding
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(transparent)]
+pub struct Controls(BTreeMap<Control, ControlBinding>);
+
+impl Controls {
+    pub fn get(&self, control: Control) -> ControlBinding {
+        self.0
+            .get(&control)
+            .copied()
+            .unwrap_or_else(|| control.default_binding())
+    }
+
+    /// Binds `control`, taking the input away from whatever else had it.
+    /// Returns the controls that lost it.
+    pub fn bind(&mut self, control: Control, input: CapturedInput) -> Vec<Control> {
+        let mut conflicts = Vec::new();
+        for other in Control::ALL {
+            let mut binding = self.get(other);
+            let taken = match input {
+                CapturedInput::Desktop(input) => {
+                    binding.keyboard.take_if(|b| *b == input).is_some()
+                }
+                CapturedInput::Gamepad(input) => binding.gamepad.take_if(|b| *b == input).is_some(),
+            };
+            if taken && other != control {
+                conflicts.push(other);
+            }
+            self.set(other, binding);
+        }
+
+        let mut binding = self.get(control);
+        match input {
+            CapturedInput::Desktop(input) => binding.keyboard = Some(input),
+            CapturedInput::Gamepad(input) => binding.gamepad = Some(input),
+        }
+        self.set(control, binding);
+        conflicts
+    }
+
+    /// Only overrides are stored, so changing a default reaches old saves
+    fn set(&mut self, control: Control, binding: ControlBinding) {
+        if binding == control.default_binding() {
+            self.0.remove(&control);
+        } else {
+            self.0.insert(control, binding);
+        }
+    }
+
+    fn bindings(&self, control: Control) -> SpawnIter<std::vec::IntoIter<Binding>> {
+        SpawnIter(self.get(control).bindings().into_iter())
+    }
+
+    /// Keyboard label, or the gamepad one if there's no key
+    pub fn label(&self, control: Control) -> String {
+        let binding = self.get(control);
+        match (binding.keyboard, binding.gamepad) {
+            (Some(input), _) => input.label(),
+            (None, Some(button)) => format!("{button:?}"),
+            (None, None) => "unbound".into(),
+        }
+    }
+
+    /// The controls hint in the corner
+    pub fn text(&self) -> String {
+        let movement: Vec<_> = [
+            Control::MoveForward,
+            Control::MoveLeft,
+            Control::MoveBack,
+            Control::MoveRight,
+        ]
+        .map(|control| self.label(control))
+        .into();
+        // "WASD" reads better than "W/A/S/D"
+        let movement = if movement.iter().all(|label| label.chars().count() == 1) {
+            movement.concat()
+        } else {
+            movement.join("/")
+        };
+
+        let mut text = format!("Controls:\n{movement}: move\n");
+        for control in [
+            Control::Jump,
+            Control::Crouch,
+            Control::Sprint,
+            Control::PullObject,
+            Control::ThrowObject,
+            Control::Interact,
+            Control::Inspect,
+        ] {
+            text += &format!("{}: {}\n", self.label(control), control.label());
+        }
+        text += "Esc: free mouse\nclick here to rebind";
+        text
+    }
+}
+
+/// Player settings that can be changed on the controls screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayerOption {
+    MouseSensitivity,
+    GamepadSensitivity,
+    Fov,
+    InvertY,
+    ToggleCrouch,
+    HeadBob,
+    LandingDip,
+}
+
+impl PlayerOption {
+    pub const ALL: [PlayerOption; 7] = [
+        PlayerOption::MouseSensitivity,
+        PlayerOption::GamepadSensitivity,
+        PlayerOption::Fov,
+        PlayerOption::InvertY,
+        PlayerOption::ToggleCrouch,
+        PlayerOption::HeadBob,
+        PlayerOption::LandingDip,
+    ];
+
+    fn label(self, player: &PlayerSettings) -> String {
+        match self {
+            PlayerOption::MouseSensitivity => {
+                format!("mouse sensitivity {:.3}", player.mouse_sensitivity)
+            }
+            PlayerOption::GamepadSensitivity => {
+                format!("gamepad sensitivity {:.1}", player.gamepad_sensitivity)
+            }
+            PlayerOption::Fov => format!("field of view {:.0}°", player.fov),
+            PlayerOption::InvertY => "invert look".into(),
+            PlayerOption::ToggleCrouch => "crouch".into(),
+            PlayerOption::HeadBob => "head bob".into(),
+            PlayerOption::LandingDip => "landing dip".into(),
+        }
+    }
+
+    /// `-` and `+` for numbers, a switch showing the state otherwise
+    fn buttons(self, player: &PlayerSettings) -> Vec<(String, ControlsAction)> {
+        let switch = match self {
+            PlayerOption::InvertY => Some(on_off(player.invert_y)),
+            PlayerOption::HeadBob => Some(on_off(player.head_bob)),
+            PlayerOption::LandingDip => Some(on_off(player.landing_dip)),
+            PlayerOption::ToggleCrouch => Some(if player.toggle_crouch {
+                "toggle"
+            } else {
+                "hold"
+            }),
+            _ => None,
+        };
+        match switch {
+            Some(state) => vec![(state.into(), ControlsAction::Adjust(self, 1))],
+            None => vec![
+                ("-".into(), ControlsAction::Adjust(self, -1)),
+                ("+".into(), ControlsAction::Adjust(self, 1)),
+            ],
+        }
+    }
+
+    /// Moves a number `steps` notches, flips a switch
+    fn adjust(self, player: &mut PlayerSettings, steps: i32) {
+        // sensitivities step by a quarter so small values stay usable
+        let factor = 1.25_f32.powi(steps);
+        match self {
+            PlayerOption::MouseSensitivity => {
+                player.mouse_sensitivity = (player.mouse_sensitivity * factor).clamp(0.01, 0.5);
+            }
+            PlayerOption::GamepadSensitivity => {
+                player.gamepad_sensitivity = (player.gamepad_sensitivity * factor).clamp(0.5, 20.0);
+            }
+            PlayerOption::Fov => {
+                player.fov = (player.fov + 5.0 * steps as f32).clamp(30.0, 110.0);
+            }
+            PlayerOption::InvertY => player.invert_y = !player.invert_y,
+            PlayerOption::ToggleCrouch => player.toggle_crouch = !player.toggle_crouch,
+            PlayerOption::HeadBob => player.head_bob = !player.head_bob,
+            PlayerOption::LandingDip => player.landing_dip = !player.landing_dip,
+        }
+    }
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on { "on" } else { "off" }
+}
+
+/// A button pressed while rebinding
+pub enum CapturedInput {
+    Desktop(DesktopInput),
+    Gamepad(GamepadButton),
+}
+
+/// Crouch while [`PlayerSettings::toggle_crouch`] is set, the crouch bindings move over to it
+#[derive(Debug, InputAction)]
+#[action_output(bool)]
+struct ToggleCrouch;
+
+/// Marks the look bindings so their sensitivity can be changed in place
+#[derive(Component, Clone)]
+pub struct MouseLook;
+
+#[derive(Component, Clone)]
+pub struct StickLook;
+
+fn look_invert(player: &PlayerSettings) -> Negate {
+    Negate {
+        x: false,
+        y: player.invert_y,
+        z: false,
+    }
+}
+
+pub fn player_actions(controls: &Controls, player: &PlayerSettings) -> impl Bundle {
+    let movement = [
+        Control::MoveForward,
+        Control::MoveBack,
+        Control::MoveLeft,
+        Control::MoveRight,
+    ]
+    .map(|control| controls.get(control));
+    let crouch = controls.bindings(Control::Crouch);
+    let none = SpawnIter(Vec::new().into_iter());
+    let (hold_crouch, toggle_crouch) = if player.toggle_crouch {
+        (none, crouch)
+    } else {
+        (crouch, none)
+    };
+
+    actions!(PlayerInput[
+        (
+            Action::<Movement>::new(),
+            // Normalize the input vector
+            DeadZone::default(),
+            Bindings::spawn((
+                SpawnWith(move |spawner: &mut RelatedSpawner<BindingOf>| {
+                    let [forward, back, left, right] = movement;
+                    for binding in forward.bindings() {
+                        spawner.spawn((binding, SwizzleAxis::YXZ));
+                    }
+                    for binding in back.bindings() {
+                        spawner.spawn((binding, Negate::all(), SwizzleAxis::YXZ));
+                    }
+                    for binding in left.bindings() {
+                        spawner.spawn((binding, Negate::all()));
+                    }
+                    for binding in right.bindings() {
+                        spawner.spawn(binding);
+                    }
+                }),
+                Axial::left_stick(),
+            ))
+        ),
+        (
+            Action::<Jump>::new(),
+            Bindings::spawn(controls.bindings(Control::Jump)),
+        ),
+        (
+            Action::<Crouch>::new(),
+            Bindings::spawn(hold_crouch),
+        ),
+        (
+            Action::<ToggleCrouch>::new(),
+            Press::default(),
+            Bindings::spawn(toggle_crouch),
+        ),
+        (
+            Action::<Sprint>::new(),
+            Bindings::spawn(controls.bindings(Control::Sprint)),
+        ),
+        (
+            Action::<RotateCamera>::new(),
+            look_invert(player),
+            Bindings::spawn((
+                Spawn((MouseLook, Binding::mouse_motion(), Scale::splat(player.mouse_sensitivity))),
+                Axial::right_stick().with((
+                    StickLook,
+                    Scale::splat(player.gamepad_sensitivity),
+                    DeadZone::default(),
+                )),
+            ))
+        ),
+        (
+            Action::<PullObject>::new(),
+            ActionSettings { consume_input: true, ..default() },
+            Press::default(),
+            Bindings::spawn(controls.bindings(Control::PullObject)),
+        ),
+        // fired by the throw module once the charge is let go
+        Action::<ThrowObject>::new(),
+        (
+            Action::<ChargeThrow>::new(),
+            ActionSettings { consume_input: true, ..default() },
+            Bindings::spawn(controls.bindings(Control::ThrowObject)),
+        ),
+        (
+            Action::<Interact>::new(),
+            Bindings::spawn(controls.bindings(Control::Interact)),
+        ),
+        (
+            Action::<Inspect>::new(),
+            Press::default(),
+            Bindings::spawn(controls.bindings(Control::Inspect)),
+        ),
+    ])
+}
+
+pub fn debug_actions(controls: &Controls) -> impl Bundle {
+    actions!(DebugInput[
+        (
+            Action::<Reset>::new(),
+            Bindings::spawn(controls.bindings(Control::Reset)),
+            Release::default(),
+        ),
+        (
+            Action::<ToggleDebug>::new(),
+            Bindings::spawn(controls.bindings(Control::ToggleDebug)),
+            Release::default(),
+        ),
+    ])
+}
+
+/// Rebuilds the input contexts and the hint whenever the bindings change
+fn apply_controls(
+    mut cmd: Commands,
+    settings: Res<Settings>,
+    mut applied: Local<Option<(Controls, bool)>>,
+    players: Query<Entity, With<PlayerInput>>,
+    debug: Query<Entity, With<DebugInput>>,
+    crouch: Query<&ActionMock, With<Action<Crouch>>>,
+    rotate: Query<&ActionMock, With<Action<RotateCamera>>>,
+    mut held: ResMut<HeldMocks>,
+    mut text: Single<&mut Text, With<ControlsText>>,
+) {
+    let controls = &settings.controls;
+    // switching crouch modes moves its bindings to another action
+    let toggle_crouch = settings.player.toggle_crouch;
+    if applied
+        .as_ref()
+        .is_some_and(|(c, t)| c == controls && *t == toggle_crouch)
+    {
+        return;
+    }
+    // a held toggle crouch doesn't carry over to hold mode
+    held.crouch = crouch.iter().next().cloned().filter(|_| toggle_crouch);
+    held.rotate = rotate.iter().next().cloned();
+    for player in &players {
+        cmd.entity(player)
+            .despawn_related::<Actions<PlayerInput>>()
+            .insert(player_actions(controls, &settings.player));
+    }
+    for entity in &debug {
+        cmd.entity(entity)
+            .despawn_related::<Actions<DebugInput>>()
+            .insert(debug_actions(controls));
+    }
+    text.0 = controls.text();
+    *applied = Some((controls.clone(), toggle_crouch));
+}
+
+/// Mocks on the actions [`apply_controls`] despawned, like a toggled crouch or the
+/// still camera while inspecting
+#[derive(Resource, Default)]
+struct HeldMocks {
+    crouch: Option<ActionMock>,
+    rotate: Option<ActionMock>,
+}
+
+/// Puts the held mocks back on the rebuilt actions
+fn restore_mocks(
+    mut cmd: Commands,
+    mut held: ResMut<HeldMocks>,
+    crouch: Query<Entity, With<Action<Crouch>>>,
+    rotate: Query<Entity, With<Action<RotateCamera>>>,
+) {
+    if let (Some(mock), Ok(action)) = (held.crouch.take(), crouch.single()) {
+        cmd.entity(action).insert(mock);
+    }
+    if let (Some(mock), Ok(action)) = (held.rotate.take(), rotate.single()) {
+        cmd.entity(action).insert(mock);
+    }
+}
+
+/// Sensitivity and invert are changed on the live modifiers, dragging them around
+/// shouldn't reset the actions
+fn apply_look(
+    settings: Res<Settings>,
+    mut mouse: Query<&mut Scale, (With<MouseLook>, Without<StickLook>)>,
+    mut stick: Query<&mut Scale, (With<StickLook>, Without<MouseLook>)>,
+    mut rotate: Query<&mut Negate, With<Action<RotateCamera>>>,
+) {
+    let player = &settings.player;
+    for mut scale in &mut mouse {
+        *scale = Scale::splat(player.mouse_sensitivity);
+    }
+    for mut scale in &mut stick {
+        *scale = Scale::splat(player.gamepad_sensitivity);
+    }
+    for mut negate in &mut rotate {
+        *negate = look_invert(player);
+    }
+}
+
+/// Holds crouch down by mocking it until the next press
+fn toggle_crouch(
+    _: On<Fire<ToggleCrouch>>,
+    mut cmd: Commands,
+    crouch: Query<(Entity, Has<ActionMock>), With<Action<Crouch>>>,
+) {
+    for (action, crouching) in &crouch {
+        if crouching {
+            cmd.entity(action).remove::<ActionMock>();
+        } else {
+            cmd.entity(action)
+                .insert(ActionMock::new(ActionState::Fired, true, MockSpan::Manual));
+        }
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub enum ControlsAction {
+    Rebind(Control, Slot),
+    /// Steps a player setting, see [`PlayerOption::adjust`]
+    Adjust(PlayerOption, i32),
+    /// Turns a part of the K post process on or off
+    ToggleEffect(KEffects),
+    /// Steps through the [`crate::k::KLook`]s
+    NextLook,
+    Defaults,
+    Close,
+}
+
+#[derive(Component)]
+pub struct ControlsButton(pub ControlsAction);
+
+#[derive(Component, Default)]
+pub struct ControlsScreen {
+    /// waiting for the next input for this
+    capturing: Option<(Control, Slot)>,
+    /// everything was let go since capturing started, so the button that started it
+    /// isn't taken as the new binding
+    armed: bool,
+    status: String,
+}
+
+impl ControlsScreen {
+    pub fn is_capturing(&self) -> bool {
+        self.capturing.is_some()
+    }
+}
+
+/// Opens the rebinding screen, observes the controls hint
+pub fn open_controls(
+    _: On<UiActivate>,
+    mut cmd: Commands,
+    screens: Query<(), With<ControlsScreen>>,
+) {
+    if !screens.is_empty() {
+        return;
+    }
+    cmd.spawn((ControlsScreen::default(), controls_screen()))
+        .observe(on_controls_click);
+}
+
+fn on_controls_click(on: On<UiActivate>, buttons: Query<&ControlsButton>, mut cmd: Commands) {
+    if let Ok(button) = buttons.get(on.original_event_target()) {
+        cmd.trigger(button.0);
+    }
+}
+
+fn on_controls_action(
+    on: On<ControlsAction>,
+    mut cmd: Commands,
+    screen: Single<(Entity, &mut ControlsScreen)>,
+    mut settings: ResMut<Settings>,
+) {
+    let (entity, mut screen) = screen.into_inner();
+    match *on {
+        ControlsAction::Rebind(control, slot) => {
+            screen.capturing = Some((control, slot));
+            screen.armed = false;
+            screen.status = format!("press a button for {}, Esc cancels", control.label());
+        }
+        ControlsAction::Adjust(option, steps) => {
+            option.adjust(&mut settings.player, steps);
+        }
+        ControlsAction::ToggleEffect(effect) => settings.graphics.k_effects.toggle(effect),
+        ControlsAction::NextLook => settings.graphics.k_look = settings.graphics.k_look.next(),
+        ControlsAction::Defaults => {
+            settings.controls = Controls::default();
+            settings.player = PlayerSettings::default();
+            settings.graphics = Graphics::default();
+            screen.capturing = None;
+            screen.status = "back to the defaults".into();
+        }
+        ControlsAction::Close => cmd.entity(entity).despawn(),
+    }
+}
+
+fn capture_input(
+    screen: Option<Single<&mut ControlsScreen>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<&Gamepad>,
+    mut settings: ResMut<Settings>,
+) {
+    let Some(mut screen) = screen else {
+        return;
+    };
+    let Some((control, slot)) = screen.capturing else {
+        return;
+    };
+    if keys.just_pressed(KeyCode::Escape) {
+        screen.capturing = None;
+        screen.status.clear();
+        return;
+    }
+    if !screen.armed {
+        let held = keys.get_pressed().next().is_some()
+            || mouse.get_pressed().next().is_some()
+            || gamepads
+                .iter()
+                .any(|gamepad| gamepad.get_pressed().next().is_some());
+        screen.armed = !held;
+        return;
+    }
+
+    let input = match slot {
+        Slot::Keyboard => keys
+            .get_just_pressed()
+            .next()
+            .map(|key| DesktopInput::Key(*key))
+            .or_else(|| {
+                // left click is how the buttons are pressed
+                mouse
+                    .get_just_pressed()
+                    .find(|button| **button != MouseButton::Left)
+                    .map(|button| DesktopInput::Mouse(*button))
+            })
+            .map(CapturedInput::Desktop),
+        Slot::Gamepad => gamepads
+            .iter()
+            .find_map(|gamepad| gamepad.get_just_pressed().next().copied())
+            .map(CapturedInput::Gamepad),
+    };
+    let Some(input) = input else {
+        return;
+    };
+
+    let conflicts = settings.controls.bind(control, input);
+    screen.capturing = None;
+    screen.status = if conflicts.is_empty() {
+        String::new()
+    } else {
+        let taken: Vec<_> = conflicts.iter().map(|c| c.label()).collect();
+        format!("unbound it from {}", taken.join(", "))
+    };
+}
+
+fn show_controls(
+    mut cmd: Commands,
+    settings: Res<Settings>,
+    screen: Option<Single<Ref<ControlsScreen>>>,
+    list: Option<Single<Entity, With<ControlsList>>>,
+    options: Option<Single<Entity, With<OptionsList>>>,
+    mut status: Option<Single<&mut Text, With<ControlsStatus>>>,
+) {
+    let (Some(screen), Some(list), Some(options)) = (screen, list, options) else {
+        return;
+    };
+    if !screen.is_changed() && !settings.is_changed() {
+        return;
+    }
+    if let Some(status) = &mut status {
+        status.0 = screen.status.clone();
+    }
+
+    let mut list = cmd.entity(*list);
+    list.despawn_children();
+    // not a button, so it's not rebindable
+    list.with_child(controls_row(
+        "look around",
+        "Mouse".into(),
+        "Right stick".into(),
+        None,
+    ));
+    for control in Control::ALL {
+        let binding = settings.controls.get(control);
+        let label = |slot, text: Option<String>| {
+            if screen.capturing == Some((control, slot)) {
+                "...".to_owned()
+            } else {
+                text.unwrap_or_else(|| "-".into())
+            }
+        };
+        list.with_child(controls_row(
+            control.label(),
+            label(Slot::Keyboard, binding.keyboard.map(DesktopInput::label)),
+            label(Slot::Gamepad, binding.gamepad.map(|b| format!("{b:?}"))),
+            Some(control),
+        ));
+    }
+
+    let mut options = cmd.entity(*options);
+    options.despawn_children();
+    for option in PlayerOption::ALL {
+        options.with_child(option_row(
+            option.label(&settings.player),
+            option.buttons(&settings.player),
+        ));
+    }
+    let look = settings.graphics.k_look;
+    options.with_child(option_row(
+        "trip".into(),
+        vec![(look.label().into(), ControlsAction::NextLook)],
+    ));
+    let enabled = settings.graphics.k_effects;
+    for (label, effect) in KEffects::labeled() {
+        let state = on_off(enabled.contains(effect));
+        options.with_child(option_row(
+            format!("effect: {label}"),
+            vec![(state.into(), ControlsAction::ToggleEffect(effect))],
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind_stores_only_overrides() {
+        let mut controls = Controls::default();
+        let conflicts = controls.bind(Control::Jump, CapturedInput::Desktop(KeyCode::KeyW.into()));
+        assert_eq!(conflicts, [Control::MoveForward]);
+        assert_eq!(controls.0.len(), 2);
+
+        controls.bind(Control::Jump, CapturedInput::Desktop(KeyCode::Space.into()));
+        controls.bind(
+            Control::MoveForward,
+            CapturedInput::Desktop(KeyCode::KeyW.into()),
+        );
+        assert_eq!(controls, Controls::default());
+    }
+
+    #[test]
+    fn bind_swaps_within_a_slot() {
+        let mut controls = Controls::default();
+        let conflicts = controls.bind(Control::Reset, CapturedInput::Gamepad(GamepadButton::South));
+        assert_eq!(conflicts, [Control::Jump]);
+        assert_eq!(controls.get(Control::Jump).gamepad, None);
+        // the keyboard slots don't care
+        assert_eq!(
+            controls.get(Control::Jump).keyboard,
+            Some(KeyCode::Space.into())
+        );
+        assert_eq!(
+            controls.get(Control::Reset).keyboard,
+            Some(KeyCode::KeyR.into())
+        );
+        assert_eq!(
+            controls.get(Control::Reset).gamepad,
+            Some(GamepadButton::South)
+        );
+
+        let conflicts = controls.bind(Control::Jump, CapturedInput::Desktop(KeyCode::KeyR.into()));
+        assert_eq!(conflicts, [Control::Reset]);
+        assert_eq!(controls.get(Control::Reset).keyboard, None);
+        assert_eq!(
+            controls.get(Control::Reset).gamepad,
+            Some(GamepadButton::South)
+        );
+    }
+
+    #[test]
+    fn rebinding_to_itself_is_no_conflict() {
+        let mut controls = Controls::default();
+        let conflicts = controls.bind(Control::Jump, CapturedInput::Desktop(KeyCode::Space.into()));
+        assert!(conflicts.is_empty());
+        assert_eq!(controls, Controls::default());
+    }
+
+    #[test]
+    fn controls_round_trip_through_ron() {
+        let mut controls = Controls::default();
+        controls.bind(
+            Control::PullObject,
+            CapturedInput::Desktop(MouseButton::Middle.into()),
+        );
+        controls.bind(
+            Control::ThrowObject,
+            CapturedInput::Gamepad(GamepadButton::East),
+        );
+        controls.bind(Control::Jump, CapturedInput::Desktop(KeyCode::KeyW.into()));
+
+        let ron = ron::ser::to_string_pretty(&controls, default()).unwrap();
+        let loaded: Controls = ron::de::from_str(&ron).unwrap();
+        assert_eq!(loaded, controls);
+        assert_eq!(loaded.get(Control::MoveForward).keyboard, None);
+        assert_eq!(
+            loaded.get(Control::PullObject).keyboard,
+            Some(MouseButton::Middle.into())
+        );
+    }
+
+    #[test]
+    fn missing_controls_load_as_defaults() {
+        let loaded: Controls = ron::de::from_str("{}").unwrap();
+        assert_eq!(loaded, Controls::default());
+        assert_eq!(
+            loaded.get(Control::Crouch),
+            Control::Crouch.default_binding()
+        );
+    }
+}
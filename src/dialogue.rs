@@ -1,19 +1,47 @@
 use bevy::prelude::*;
-use bevy_seedling::sample::SamplePlayer;
+use bevy_easy_gif::GifAsset;
+use bevy_seedling::sample::{AudioSample, SamplePlayer};
 
 use crate::{
-    player::DisablePlayer,
+    loading::Preload,
+    ui_input::UiActivate,
     widgets::{DialogueTypewriter, dialogue_box, dismiss_ui},
 };
 
-pub fn intro(mut cmd: Commands, ass: Res<AssetServer>) {
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(Startup, load_assets);
+}
+
+/// Portraits and voices, preloaded so they don't pop in
+#[derive(Resource)]
+pub struct DialogueAssets {
+    orange_man: Handle<GifAsset>,
+    applestein: Handle<GifAsset>,
+    orange_talk: Handle<AudioSample>,
+    enchantment: Handle<AudioSample>,
+}
+
+fn load_assets(mut cmd: Commands, ass: Res<AssetServer>, mut preload: ResMut<Preload>) {
+    let assets = DialogueAssets {
+        orange_man: ass.load("orangeman.gif"),
+        applestein: ass.load("applestein.gif"),
+        orange_talk: ass.load("orange_talk.ogg"),
+        enchantment: ass.load("enchantment.ogg"),
+    };
+    preload.add(assets.orange_man.clone());
+    preload.add(assets.applestein.clone());
+    preload.add(assets.orange_talk.clone());
+    preload.add(assets.enchantment.clone());
+    cmd.insert_resource(assets);
+}
+
+pub fn intro(mut cmd: Commands, ass: Res<DialogueAssets>) {
     cmd.spawn((
         DialogueTypewriter::new(30.),
-        dialogue_box("Orange Man", "I lost some files i mean no the files are strategically placed in the orfice. I am a money man i dont have time sorting through bureaucracy, the money today is in making stuff like fruit bowlia, like making it a really good place again. Anyways return the files to me and no peeking", ass.load("orangeman.gif")),
-        SamplePlayer::new(ass.load("orange_talk.ogg")).looping(),
+        dialogue_box("Orange Man", "I lost some files i mean no the files are strategically placed in the orfice. I am a money man i dont have time sorting through bureaucracy, the money today is in making stuff like fruit bowlia, like making it a really good place again. Anyways return the files to me and no peeking", ass.orange_man.clone()),
+        SamplePlayer::new(ass.orange_talk.clone()).looping(),
     ))
     .observe(dismiss_ui);
-    cmd.trigger(DisablePlayer);
 }
 
 #[derive(Reflect, Clone, Copy)]
@@ -41,96 +69,90 @@ impl Command for StartDialogue {
     }
 }
 
-fn relax(mut cmd: Commands, ass: Res<AssetServer>) {
+fn relax(mut cmd: Commands, ass: Res<DialogueAssets>) {
     cmd.spawn((
         DialogueTypewriter::new(15.),
-        dialogue_box("Orange Man", "Relax Guy", ass.load("orangeman.gif")),
-        SamplePlayer::new(ass.load("orange_talk.ogg")).looping(),
+        dialogue_box("Orange Man", "Relax Guy", ass.orange_man.clone()),
+        SamplePlayer::new(ass.orange_talk.clone()).looping(),
     ))
     .observe(dismiss_ui);
-    cmd.trigger(DisablePlayer);
 }
 
-fn romance(mut cmd: Commands, ass: Res<AssetServer>) {
+fn romance(mut cmd: Commands, ass: Res<DialogueAssets>) {
     cmd.spawn((
         DialogueTypewriter::new(15.),
         dialogue_box(
             "Applestein",
             "ꖎᒷᒲᒲᒷ ⊣𝙹ᒷʖʖᒷꖎᓭ ℸ⍑ᔑℸ ∴ᒷᒷリ╎ᒷ",
-            ass.load("applestein.gif"),
+            ass.applestein.clone(),
         ),
-        SamplePlayer::new(ass.load("enchantment.ogg")),
+        SamplePlayer::new(ass.enchantment.clone()),
     ))
     .observe(romance2);
-    cmd.trigger(DisablePlayer);
 }
 
-fn romance2(on: On<Pointer<Click>>, mut cmd: Commands, ass: Res<AssetServer>) {
+fn romance2(on: On<UiActivate>, mut cmd: Commands, ass: Res<DialogueAssets>) {
     cmd.entity(on.entity).despawn();
     cmd.spawn((
         DialogueTypewriter::new(15.),
         dialogue_box(
             "Orange Man",
             "Oh Applestein, show me what you did with Bubb - Oh you're back already, what do you want? Get back to work",
-            ass.load("orangeman.gif"),
+            ass.orange_man.clone(),
         ),
-        SamplePlayer::new(ass.load("orange_talk.ogg")),
+        SamplePlayer::new(ass.orange_talk.clone()),
     ))
     .observe(dismiss_ui);
 }
 
-fn antichrist(mut cmd: Commands, ass: Res<AssetServer>) {
+fn antichrist(mut cmd: Commands, ass: Res<DialogueAssets>) {
     cmd.spawn((
         DialogueTypewriter::new(15.),
         dialogue_box(
             "Orange Man",
             "Please stop talking to me about the Antichrist",
-            ass.load("orangeman.gif"),
+            ass.orange_man.clone(),
         ),
-        SamplePlayer::new(ass.load("orange_talk.ogg")).looping(),
+        SamplePlayer::new(ass.orange_talk.clone()).looping(),
     ))
     .observe(dismiss_ui);
-    cmd.trigger(DisablePlayer);
 }
 
-fn khole(mut cmd: Commands, ass: Res<AssetServer>) {
+fn khole(mut cmd: Commands, ass: Res<DialogueAssets>) {
     cmd.spawn((
         DialogueTypewriter::new(15.),
         dialogue_box(
             "Orange Man",
             "AAAAAAAAAAAAAAAAAND im in a k-hole",
-            ass.load("orangeman.gif"),
+            ass.orange_man.clone(),
         ),
-        SamplePlayer::new(ass.load("orange_talk.ogg")).looping(),
+        SamplePlayer::new(ass.orange_talk.clone()).looping(),
     ))
     .observe(dismiss_ui);
-    cmd.trigger(DisablePlayer);
 }
 
-fn acid(mut cmd: Commands, ass: Res<AssetServer>) {
+fn acid(mut cmd: Commands, ass: Res<DialogueAssets>) {
     cmd.spawn((
         DialogueTypewriter::new(25.),
         dialogue_box(
             "Applestein",
             "ᓵᔑリ ╎ ⊣ᒷℸ ᔑ ℸᔑ̇/ ∷ᒷℸ⚍∷リ 𝙹リ ℸ⍑ᒷ ᓭ⚍ꖎ⎓⚍∷╎ᓵ ᔑᓵ╎↸ ╎ ⚍ᓭᒷ↸ ⎓𝙹∷ ↸╎ᓭᓭ𝙹ꖎ⍊╎リ⊣ ℸ⍑ᒷ ᓵ⍑╎ꖎ↸∷ᒷリ ∴ᒷ ᔑʖ⚍ᓭᒷ↸?",
-            ass.load("applestein.gif"),
+            ass.applestein.clone(),
         ),
-        SamplePlayer::new(ass.load("enchantment.ogg")).looping(),
+        SamplePlayer::new(ass.enchantment.clone()).looping(),
     ))
     .observe(dismiss_ui);
-    cmd.trigger(DisablePlayer);
 }
 
-fn women(mut cmd: Commands, ass: Res<AssetServer>) {
+fn women(mut cmd: Commands, ass: Res<DialogueAssets>) {
     cmd.spawn((
         DialogueTypewriter::new(30.),
         dialogue_box(
             "Orange Man",
             "Women i have respect for woman, maybe the most respect anyone has for them. Friends of mine are women believe it or not i have lots of friends old ones young ones it doesnt matter its all the same to me",
-            ass.load("orangeman.gif"),
+            ass.orange_man.clone(),
         ),
-        SamplePlayer::new(ass.load("orange_talk.ogg")).looping(),
+        SamplePlayer::new(ass.orange_talk.clone()).looping(),
     ))
     .observe(dismiss_ui);
-    cmd.trigger(DisablePlayer);
 }
@@ -0,0 +1,479 @@
+//! Turns the raw bytes of a [`Blob`](crate::blob::Blob) into something the viewer can show.
+//!
+//! The pdf support is deliberately tiny: it only pulls the text out of uncompressed
+//! or Flate compressed content streams, which is enough for the ocr'd files we ship.
+
+use miniz_oxide::inflate::decompress_to_vec_zlib;
+
+/// Lines of text shown on one viewer page
+pub const LINES_PER_PAGE: usize = 24;
+
+pub enum Document {
+    /// Paginated text, used for pdfs and plain text files
+    Text(Vec<String>),
+    /// An image, tagged with the file extension of its format
+    Image(&'static str),
+}
+
+impl Document {
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"%PDF") {
+            return Some(Document::Text(paginate(&pdf_text(bytes), LINES_PER_PAGE)));
+        }
+        if let Some(ext) = image_extension(bytes) {
+            return Some(Document::Image(ext));
+        }
+        let text = std::str::from_utf8(bytes).ok()?;
+        Some(Document::Text(paginate(&[text.to_owned()], LINES_PER_PAGE)))
+    }
+}
+
+fn image_extension(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("png")
+    } else if bytes.starts_with(b"\xff\xd8\xff") {
+        Some("jpg")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("webp")
+    } else {
+        None
+    }
+}
+
+/// Splits every section into pages of at most `lines` lines.
+/// A section never shares a page with another one.
+pub fn paginate(sections: &[String], lines: usize) -> Vec<String> {
+    let mut pages = Vec::new();
+    for section in sections {
+        let section_lines: Vec<&str> = section.trim().lines().collect();
+        for chunk in section_lines.chunks(lines.max(1)) {
+            pages.push(chunk.join("\n"));
+        }
+    }
+    if pages.is_empty() {
+        pages.push(String::new());
+    }
+    pages
+}
+
+/// Extracts the text of every content stream, one entry per stream that contained any.
+pub fn pdf_text(pdf: &[u8]) -> Vec<String> {
+    let mut texts = Vec::new();
+    let mut cursor = 0;
+    while let Some((dict, data, end)) = next_stream(pdf, cursor) {
+        cursor = end;
+        if contains(dict, b"/Image") || contains(dict, b"/ObjStm") || contains(dict, b"/XRef") {
+            continue;
+        }
+        let decoded = if !contains(dict, b"/Filter") {
+            data.to_vec()
+        } else if contains(dict, b"/FlateDecode") && !contains(dict, b"/DecodeParms") {
+            match decompress_to_vec_zlib(data) {
+                Ok(decoded) => decoded,
+                Err(_) => continue,
+            }
+        } else {
+            continue;
+        };
+        let text = content_text(&decoded);
+        if !text.trim().is_empty() {
+            texts.push(text);
+        }
+    }
+    texts
+}
+
+/// Finds the next `stream ... endstream` pair after `from`,
+/// returning the stream dictionary, the raw data and the end offset.
+fn next_stream(pdf: &[u8], from: usize) -> Option<(&[u8], &[u8], usize)> {
+    let mut search = from;
+    loop {
+        let keyword = search + find(&pdf[search..], b"stream")?;
+        // skip the "stream" inside "endstream"
+        if keyword >= 3 && &pdf[keyword - 3..keyword] == b"end" {
+            search = keyword + 6;
+            continue;
+        }
+        let mut start = keyword + 6;
+        if pdf.get(start) == Some(&b'\r') {
+            start += 1;
+        }
+        if pdf.get(start) == Some(&b'\n') {
+            start += 1;
+        }
+        let end = start + find(&pdf[start..], b"endstream")?;
+        let dict_start = rfind(&pdf[from..keyword], b"obj").map_or(from, |i| from + i);
+        let data = trim_eol(&pdf[start..end]);
+        return Some((&pdf[dict_start..keyword], data, end + 9));
+    }
+}
+
+fn trim_eol(mut data: &[u8]) -> &[u8] {
+    while let [rest @ .., b'\r' | b'\n'] = data {
+        data = rest;
+    }
+    data
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).rposition(|w| w == needle)
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    find(haystack, needle).is_some()
+}
+
+enum Token {
+    Number(f32),
+    Str(Vec<u8>),
+    ArrayStart,
+    ArrayEnd,
+    Op(Vec<u8>),
+}
+
+/// Walks a content stream and collects the strings shown by the text operators.
+fn content_text(content: &[u8]) -> String {
+    let mut out = String::new();
+    let mut operands: Vec<Token> = Vec::new();
+    let mut last_y: Option<f32> = None;
+    let mut tokens = Tokens {
+        src: content,
+        pos: 0,
+    };
+
+    while let Some(token) = tokens.next_token() {
+        let Token::Op(op) = token else {
+            operands.push(token);
+            continue;
+        };
+        match op.as_slice() {
+            b"Tj" => push_last_string(&mut out, &operands),
+            b"'" | b"\"" => {
+                newline(&mut out);
+                push_last_string(&mut out, &operands);
+            }
+            b"TJ" => {
+                for operand in &operands {
+                    match operand {
+                        Token::Str(s) => out.push_str(&decode(s)),
+                        // large negative kerning is how most generators encode a space
+                        Token::Number(n) if *n < -200.0 => out.push(' '),
+                        _ => {}
+                    }
+                }
+            }
+            b"T*" => newline(&mut out),
+            b"Td" | b"TD" => {
+                if let [.., Token::Number(x), Token::Number(y)] = operands.as_slice() {
+                    if *y != 0.0 {
+                        newline(&mut out);
+                    } else if *x > 0.0 && !out.ends_with(' ') {
+                        out.push(' ');
+                    }
+                }
+            }
+            b"Tm" => {
+                if let [.., Token::Number(y)] = operands.as_slice() {
+                    if last_y.is_some_and(|last| (last - y).abs() > 0.5) {
+                        newline(&mut out);
+                    } else if !out.is_empty() && !out.ends_with([' ', '\n']) {
+                        out.push(' ');
+                    }
+                    last_y = Some(*y);
+                }
+            }
+            b"ET" if !out.is_empty() && !out.ends_with([' ', '\n']) => out.push(' '),
+            _ => {}
+        }
+        operands.clear();
+    }
+    out
+}
+
+fn newline(out: &mut String) {
+    let trimmed = out.trim_end_matches(' ').len();
+    out.truncate(trimmed);
+    if !out.is_empty() {
+        out.push('\n');
+    }
+}
+
+fn push_last_string(out: &mut String, operands: &[Token]) {
+    if let Some(Token::Str(s)) = operands.iter().rev().find(|t| matches!(t, Token::Str(_))) {
+        out.push_str(&decode(s));
+    }
+}
+
+/// Our files only use the standard fonts with WinAnsiEncoding, latin-1 is close enough
+fn decode(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| match b {
+            0x91 | 0x92 => '\'',
+            0x93 | 0x94 => '"',
+            0x96 | 0x97 => '-',
+            b => b as char,
+        })
+        .collect()
+}
+
+struct Tokens<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        loop {
+            let c = self.peek()?;
+            match c {
+                b' ' | b'\t' | b'\r' | b'\n' | b'\x0c' | b'\0' => self.pos += 1,
+                b'%' => {
+                    while self.peek().is_some_and(|c| c != b'\n' && c != b'\r') {
+                        self.pos += 1;
+                    }
+                }
+                b'(' => return Some(Token::Str(self.literal())),
+                b'<' if self.src.get(self.pos + 1) == Some(&b'<') => {
+                    // inline dictionaries (marked content properties) carry no text
+                    self.skip_dict();
+                }
+                b'<' => return Some(Token::Str(self.hex())),
+                b'[' => {
+                    self.pos += 1;
+                    return Some(Token::ArrayStart);
+                }
+                b']' => {
+                    self.pos += 1;
+                    return Some(Token::ArrayEnd);
+                }
+                b'/' => {
+                    self.pos += 1;
+                    self.word();
+                }
+                b'+' | b'-' | b'.' | b'0'..=b'9' => {
+                    let word = self.word();
+                    let number = std::str::from_utf8(word).ok()?.parse().unwrap_or(0.0);
+                    return Some(Token::Number(number));
+                }
+                _ => {
+                    let word = self.word();
+                    if word.is_empty() {
+                        // stray delimiter, skip it
+                        self.pos += 1;
+                        continue;
+                    }
+                    if word == b"BI" {
+                        self.skip_inline_image();
+                        continue;
+                    }
+                    return Some(Token::Op(word.to_vec()));
+                }
+            }
+        }
+    }
+
+    fn word(&mut self) -> &'a [u8] {
+        let src = self.src;
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_whitespace() || b"()<>[]{}/%".contains(&c) {
+                break;
+            }
+            self.pos += 1;
+        }
+        &src[start..self.pos]
+    }
+
+    fn literal(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut depth = 0;
+        self.pos += 1;
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                b'(' => {
+                    depth += 1;
+                    out.push(c);
+                }
+                b')' if depth == 0 => break,
+                b')' => {
+                    depth -= 1;
+                    out.push(c);
+                }
+                b'\\' => {
+                    let Some(e) = self.peek() else { break };
+                    self.pos += 1;
+                    match e {
+                        b'n' => out.push(b'\n'),
+                        b'r' => out.push(b'\r'),
+                        b't' => out.push(b'\t'),
+                        b'b' => out.push(0x08),
+                        b'f' => out.push(0x0c),
+                        b'\r' => {
+                            if self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        b'0'..=b'7' => {
+                            let mut value = u32::from(e - b'0');
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(d @ b'0'..=b'7') => {
+                                        value = value * 8 + u32::from(d - b'0');
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            out.push(value as u8);
+                        }
+                        e => out.push(e),
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+        out
+    }
+
+    fn hex(&mut self) -> Vec<u8> {
+        self.pos += 1;
+        let mut digits = Vec::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            if c == b'>' {
+                break;
+            }
+            if let Some(d) = (c as char).to_digit(16) {
+                digits.push(d as u8);
+            }
+        }
+        if digits.len() % 2 == 1 {
+            digits.push(0);
+        }
+        digits
+            .chunks(2)
+            .map(|pair| (pair[0] << 4) | pair[1])
+            .collect()
+    }
+
+    fn skip_dict(&mut self) {
+        let mut depth = 0;
+        while self.pos < self.src.len() {
+            if self.src[self.pos..].starts_with(b"<<") {
+                depth += 1;
+                self.pos += 2;
+            } else if self.src[self.pos..].starts_with(b">>") {
+                depth -= 1;
+                self.pos += 2;
+                if depth == 0 {
+                    return;
+                }
+            } else {
+                self.pos += 1;
+            }
+        }
+    }
+
+    fn skip_inline_image(&mut self) {
+        match find(&self.src[self.pos..], b"EI") {
+            Some(end) => self.pos += end + 2,
+            None => self.pos = self.src.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miniz_oxide::deflate::compress_to_vec_zlib;
+
+    use super::*;
+
+    fn pdf(dict: &str, data: &[u8]) -> Vec<u8> {
+        let mut pdf = format!("%PDF-1.4\n1 0 obj\n<< {dict} >>\nstream\n").into_bytes();
+        pdf.extend_from_slice(data);
+        pdf.extend_from_slice(b"\nendstream\nendobj\n%%EOF\n");
+        pdf
+    }
+
+    fn text(document: Option<Document>) -> Vec<String> {
+        match document {
+            Some(Document::Text(pages)) => pages,
+            _ => panic!("expected text"),
+        }
+    }
+
+    #[test]
+    fn paginate_splits_long_sections() {
+        let section = (1..=5)
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(paginate(&[section], 2), ["1\n2", "3\n4", "5"]);
+    }
+
+    #[test]
+    fn paginate_keeps_sections_apart() {
+        let pages = paginate(&["a\nb".into(), "c".into()], 10);
+        assert_eq!(pages, ["a\nb", "c"]);
+    }
+
+    #[test]
+    fn paginate_always_has_a_page() {
+        assert_eq!(paginate(&[], 10), [""]);
+        assert_eq!(paginate(&["  \n ".into()], 10), [""]);
+        // zero lines per page would never finish a page
+        assert_eq!(paginate(&["a\nb".into()], 0), ["a", "b"]);
+    }
+
+    #[test]
+    fn empty_and_garbage_input() {
+        assert_eq!(text(Document::parse(b"")), [""]);
+        assert!(Document::parse(&[0xff, 0xfe, 0x00, 0x9f]).is_none());
+        assert!(matches!(
+            Document::parse(b"\x89PNG\r\n\x1a\n...."),
+            Some(Document::Image("png"))
+        ));
+    }
+
+    #[test]
+    fn plain_pdf_text() {
+        let pdf = pdf(
+            "/Length 0",
+            b"BT /F1 12 Tf 72 700 Td (Hello) Tj 0 -14 Td [(Wor) -20 (ld)] TJ ET",
+        );
+        assert_eq!(text(Document::parse(&pdf)), ["Hello\nWorld"]);
+    }
+
+    #[test]
+    fn flate_pdf_text() {
+        let content = compress_to_vec_zlib(b"BT (zipped \\(text\\)) Tj ET", 6);
+        let pdf = pdf("/Filter /FlateDecode", &content);
+        assert_eq!(text(Document::parse(&pdf)), ["zipped (text)"]);
+    }
+
+    #[test]
+    fn malformed_pdfs_dont_panic() {
+        // no endstream
+        let mut truncated = pdf("/Length 0", b"BT (cut off) Tj ET");
+        truncated.truncate(truncated.len() - 20);
+        assert_eq!(text(Document::parse(&truncated)), [""]);
+        // not actually zlib
+        let broken = pdf("/Filter /FlateDecode", b"definitely not deflate");
+        assert_eq!(text(Document::parse(&broken)), [""]);
+        // an odd hex digit is padded with a 0, an unterminated string runs to the end
+        let strings = pdf("/Length 0", b"BT <48656C6C6F2> Tj (open");
+        assert_eq!(text(Document::parse(&strings)), ["Hello"]);
+        assert_eq!(text(Document::parse(b"%PDF")), [""]);
+    }
+}
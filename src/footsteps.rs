@@ -0,0 +1,218 @@
+//! Movement feedback for the character controller. Footsteps play every stride while
+//! walking on the ground, picked by the [`SurfaceMaterial`] of what's underfoot, and the
+//! camera bobs along with them and dips on landing.
+//!
+//! The camera offset is taken back off at the start of every frame, so it never adds up
+//! with whatever bevy_ahoy does to the camera in between.
+
+use std::f32::consts::PI;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_ahoy::prelude::*;
+use bevy_seedling::prelude::*;
+
+use crate::{GameState, loading::Preload, settings::Settings};
+
+/// Ground covered per footstep, m
+const STRIDE: f32 = 0.75;
+/// Slower than this doesn't count as walking, m/s
+const MIN_SPEED: f32 = 0.5;
+/// Walking speed the bob is tuned for, faster walks bob harder up to this
+const FULL_BOB_SPEED: f32 = 4.0;
+/// How far the camera bobs down on a step and sways sideways, m
+const BOB_HEIGHT: f32 = 0.035;
+const BOB_SWAY: f32 = 0.02;
+/// Falls slower than this land silently, m/s
+const MIN_LANDING_SPEED: f32 = 2.0;
+/// Dip depth per m/s of falling speed, and the deepest dip, m
+const DIP_PER_SPEED: f32 = 0.02;
+const MAX_DIP: f32 = 0.15;
+/// Seconds for the camera to dip and come back up
+const DIP_TIME: f32 = 0.3;
+/// Samples per surface, `footsteps/<surface>_<n>.wav`
+const VARIATIONS: usize = 3;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<Gait>()
+        .add_systems(Startup, preload_samples)
+        .add_systems(First, take_back_offset)
+        .add_systems(Update, walk.run_if(in_state(GameState::Playing)))
+        .add_systems(PostUpdate, bob_camera.before(TransformSystems::Propagate));
+}
+
+/// What a surface sounds like underfoot. Untagged ground is [`SurfaceMaterial::Concrete`]
+#[derive(Component, Reflect, Clone, Copy, Default, Debug, PartialEq, Eq)]
+#[reflect(Component, Default)]
+#[type_path = "stoned"]
+pub enum SurfaceMaterial {
+    #[default]
+    Concrete,
+    Wood,
+    Carpet,
+    Tile,
+    Metal,
+}
+
+impl SurfaceMaterial {
+    pub const ALL: [SurfaceMaterial; 5] = [
+        SurfaceMaterial::Concrete,
+        SurfaceMaterial::Wood,
+        SurfaceMaterial::Carpet,
+        SurfaceMaterial::Tile,
+        SurfaceMaterial::Metal,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            SurfaceMaterial::Concrete => "concrete",
+            SurfaceMaterial::Wood => "wood",
+            SurfaceMaterial::Carpet => "carpet",
+            SurfaceMaterial::Tile => "tile",
+            SurfaceMaterial::Metal => "metal",
+        }
+    }
+
+    /// Footstep samples, relative to the assets folder
+    pub fn samples(self) -> impl Iterator<Item = String> {
+        (1..=VARIATIONS).map(move |n| format!("footsteps/{}_{n}.wav", self.name()))
+    }
+}
+
+fn preload_samples(ass: Res<AssetServer>, mut preload: ResMut<Preload>) {
+    for path in SurfaceMaterial::ALL
+        .into_iter()
+        .flat_map(SurfaceMaterial::samples)
+    {
+        preload.add(ass.load::<AudioSample>(path));
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+struct Gait {
+    grounded: bool,
+    /// Advances half a turn per [`STRIDE`], a footstep lands on every half turn
+    phase: f32,
+    /// 0.0 - 1.0, eases in and out with the walking speed
+    bob: f32,
+    /// Fastest fall since leaving the ground, m/s
+    fall_speed: f32,
+    /// Seconds into the landing dip, and how deep it goes
+    dip: Option<(f32, f32)>,
+    /// Cycles through the variations so the same sample doesn't repeat
+    variation: usize,
+    /// Added to the camera this frame
+    applied: Vec3,
+}
+
+fn walk(
+    mut cmd: Commands,
+    time: Res<Time>,
+    ass: Res<AssetServer>,
+    mut gait: ResMut<Gait>,
+    player: Single<(&CharacterControllerState, &LinearVelocity)>,
+    surfaces: Query<&SurfaceMaterial>,
+    parents: Query<&ChildOf>,
+) {
+    let (state, velocity) = player.into_inner();
+    let dt = time.delta_secs();
+    // surfaces can be tagged on the object above the collider
+    let surface = state.grounded.as_ref().map(|ground| {
+        std::iter::once(ground.entity)
+            .chain(parents.iter_ancestors(ground.entity))
+            .find_map(|entity| surfaces.get(entity).ok().copied())
+            .unwrap_or_default()
+    });
+    let Some(surface) = surface else {
+        gait.grounded = false;
+        gait.fall_speed = gait.fall_speed.max(-velocity.y);
+        gait.bob.smooth_nudge(&0.0, 10.0, dt);
+        return;
+    };
+
+    if !gait.grounded {
+        gait.grounded = true;
+        let fall_speed = std::mem::take(&mut gait.fall_speed);
+        if fall_speed > MIN_LANDING_SPEED {
+            gait.dip = Some((0.0, (fall_speed * DIP_PER_SPEED).min(MAX_DIP)));
+            let volume = (fall_speed / 8.0).clamp(0.6, 1.5);
+            footstep(&mut cmd, &ass, &mut gait, surface, volume);
+        }
+    }
+
+    let speed = velocity.xz().length();
+    let walking = speed > MIN_SPEED;
+    let target = if walking {
+        (speed / FULL_BOB_SPEED).min(1.0)
+    } else {
+        0.0
+    };
+    gait.bob.smooth_nudge(&target, 10.0, dt);
+    if !walking {
+        return;
+    }
+
+    let step = (gait.phase / PI).floor();
+    gait.phase += speed * dt / STRIDE * PI;
+    if (gait.phase / PI).floor() > step {
+        let volume = (speed / FULL_BOB_SPEED).clamp(0.3, 1.0);
+        footstep(&mut cmd, &ass, &mut gait, surface, volume);
+    }
+}
+
+fn footstep(
+    cmd: &mut Commands,
+    ass: &AssetServer,
+    gait: &mut Gait,
+    surface: SurfaceMaterial,
+    volume: f32,
+) {
+    gait.variation = (gait.variation + 1) % VARIATIONS;
+    let sample = surface.samples().nth(gait.variation).unwrap();
+    cmd.spawn(SamplePlayer::new(ass.load(sample)).with_volume(Volume::Linear(volume)));
+}
+
+fn take_back_offset(
+    mut gait: ResMut<Gait>,
+    mut camera: Query<&mut Transform, With<CharacterControllerCameraOf>>,
+) {
+    let applied = std::mem::take(&mut gait.applied);
+    if applied == Vec3::ZERO {
+        return;
+    }
+    for mut transform in &mut camera {
+        transform.translation -= applied;
+    }
+}
+
+fn bob_camera(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut gait: ResMut<Gait>,
+    camera: Single<&mut Transform, With<CharacterControllerCameraOf>>,
+) {
+    let player = &settings.player;
+    let mut transform = camera.into_inner();
+    let mut offset = Vec3::ZERO;
+
+    if player.head_bob {
+        // lowest as a foot lands, swaying over to that foot
+        let local = Vec3::new(
+            BOB_SWAY * gait.phase.cos(),
+            -BOB_HEIGHT * gait.phase.cos().abs(),
+            0.0,
+        );
+        offset += transform.rotation * local * gait.bob;
+    }
+
+    if let Some((elapsed, depth)) = gait.dip {
+        let elapsed = elapsed + time.delta_secs();
+        gait.dip = (elapsed < DIP_TIME).then_some((elapsed, depth));
+        if player.landing_dip {
+            offset.y -= depth * (elapsed / DIP_TIME * PI).sin().max(0.0);
+        }
+    }
+
+    transform.translation += offset;
+    gait.applied = offset;
+}
@@ -0,0 +1,172 @@
+//! Inspecting the held prop. It comes up close, the background blurs with the camera's
+//! [`DepthOfField`] and the mouse or right stick turns the prop instead of the camera.
+//! Props with an [`InspectInfo`] get a description panel next to them.
+
+use avian_pickup::{
+    actor::AvianPickupActorState,
+    prop::{PreferredPickupDistanceOverride, PreferredPickupRotation},
+};
+use bevy::{input::mouse::AccumulatedMouseMotion, post_process::dof::DepthOfField, prelude::*};
+use bevy_ahoy::prelude::*;
+use bevy_enhanced_input::prelude::*;
+
+use crate::{
+    DynamicProp, GameState, InspectInfo,
+    settings::Settings,
+    widgets::{InspectPanel, inspect_panel},
+};
+
+/// How far in front of the camera the prop is held while inspected
+const INSPECT_DISTANCE: f32 = 0.45;
+/// The prop turns this much faster than the camera does for the same mouse motion
+const MOUSE_TURN: f32 = 3.0;
+/// Radians per second at full tilt, per unit of gamepad sensitivity
+const STICK_TURN: f32 = 0.5;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_observer(toggle_inspect)
+        .add_observer(on_stop_inspecting)
+        .add_systems(
+            Update,
+            (end_inspection, turn_prop)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, InputAction)]
+#[action_output(bool)]
+pub struct Inspect;
+
+/// On the player camera while a prop is inspected
+#[derive(Component)]
+struct Inspecting {
+    prop: Entity,
+    /// relative to the camera
+    rotation: Quat,
+    /// put back once the inspection ends
+    depth_of_field: DepthOfField,
+}
+
+#[derive(Event)]
+struct StopInspecting;
+
+fn toggle_inspect(
+    _: On<Fire<Inspect>>,
+    mut cmd: Commands,
+    camera: Single<
+        (Entity, &GlobalTransform, &DepthOfField, Has<Inspecting>),
+        With<CharacterControllerCameraOf>,
+    >,
+    actor: Single<&AvianPickupActorState>,
+    props: Query<(&GlobalTransform, Option<&InspectInfo>), With<DynamicProp>>,
+    rotate: Query<Entity, With<Action<RotateCamera>>>,
+) {
+    let (camera, transform, depth_of_field, inspecting) = camera.into_inner();
+    if inspecting {
+        cmd.trigger(StopInspecting);
+        return;
+    }
+    let &AvianPickupActorState::Holding(prop) = *actor else {
+        return;
+    };
+    let Ok((prop_transform, info)) = props.get(prop) else {
+        return;
+    };
+
+    // starts out the way it was held
+    let rotation = transform.rotation().inverse() * prop_transform.rotation();
+    cmd.entity(prop).insert((
+        PreferredPickupDistanceOverride(INSPECT_DISTANCE),
+        PreferredPickupRotation(rotation),
+    ));
+    cmd.entity(camera).insert((
+        Inspecting {
+            prop,
+            rotation,
+            depth_of_field: depth_of_field.clone(),
+        },
+        DepthOfField {
+            focal_distance: INSPECT_DISTANCE,
+            ..default()
+        },
+    ));
+    // the mouse turns the prop now, keep the camera still
+    for action in &rotate {
+        cmd.entity(action).insert(ActionMock::new(
+            ActionState::None,
+            Vec2::ZERO,
+            MockSpan::Manual,
+        ));
+    }
+    if let Some(info) = info {
+        cmd.spawn(inspect_panel(info));
+    }
+}
+
+fn on_stop_inspecting(
+    _: On<StopInspecting>,
+    mut cmd: Commands,
+    camera: Single<(Entity, &Inspecting)>,
+    rotate: Query<Entity, With<Action<RotateCamera>>>,
+    panels: Query<Entity, With<InspectPanel>>,
+) {
+    let (camera, inspecting) = camera.into_inner();
+    cmd.entity(inspecting.prop)
+        .try_remove::<(PreferredPickupDistanceOverride, PreferredPickupRotation)>();
+    cmd.entity(camera)
+        .remove::<Inspecting>()
+        .insert(inspecting.depth_of_field.clone());
+    for action in &rotate {
+        cmd.entity(action).remove::<ActionMock>();
+    }
+    for panel in &panels {
+        cmd.entity(panel).despawn();
+    }
+}
+
+/// Dropping or throwing the prop ends the inspection too
+fn end_inspection(
+    mut cmd: Commands,
+    inspecting: Option<Single<&Inspecting>>,
+    actor: Single<&AvianPickupActorState>,
+) {
+    let Some(inspecting) = inspecting else {
+        return;
+    };
+    if !matches!(*actor, AvianPickupActorState::Holding(prop) if *prop == inspecting.prop) {
+        cmd.trigger(StopInspecting);
+    }
+}
+
+fn turn_prop(
+    mut cmd: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mouse: Res<AccumulatedMouseMotion>,
+    gamepads: Query<&Gamepad>,
+    inspecting: Option<Single<&mut Inspecting>>,
+) {
+    let Some(mut inspecting) = inspecting else {
+        return;
+    };
+    let player = &settings.player;
+    let mut turn = mouse.delta * player.mouse_sensitivity.to_radians() * MOUSE_TURN;
+    for gamepad in &gamepads {
+        // stick up is positive, mouse up is negative
+        let stick = gamepad.right_stick() * Vec2::new(1.0, -1.0);
+        turn += stick * player.gamepad_sensitivity * STICK_TURN * time.delta_secs();
+    }
+    if player.invert_y {
+        turn.y = -turn.y;
+    }
+    if turn == Vec2::ZERO {
+        return;
+    }
+
+    // around the camera's axes, so the prop follows the mouse whichever way it's turned
+    inspecting.rotation =
+        Quat::from_rotation_y(turn.x) * Quat::from_rotation_x(turn.y) * inspecting.rotation;
+    cmd.entity(inspecting.prop)
+        .try_insert(PreferredPickupRotation(inspecting.rotation));
+}
@@ -0,0 +1,252 @@
+//! What the player is looking at. The prop or [`Interactable`] under the crosshair is
+//! highlighted and a prompt next to the crosshair says which binding does what with it.
+//!
+//! Props are picked up by the physics pickup. Interactables are not, holding
+//! [`Interact`] on one for its `hold` time triggers [`Interacted`] on it instead.
+
+use avian_pickup::actor::AvianPickupActorState;
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_ahoy::prelude::*;
+use bevy_enhanced_input::prelude::*;
+
+use crate::{
+    CollisionLayer, DynamicProp, GameState,
+    controls::Control,
+    player::PlayerInput,
+    settings::Settings,
+    widgets::{
+        InteractProgress, InteractProgressFill, InteractPrompt, InteractPromptText, ProgressFill,
+        interact_prompt,
+    },
+};
+
+/// How far away things can be targeted, about as far as the pickup reaches
+const REACH: f32 = 3.0;
+/// Added to the emissive of the target's materials
+const HIGHLIGHT: LinearRgba = LinearRgba::rgb(0.35, 0.3, 0.15);
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<LookTarget>()
+        .init_resource::<InteractHold>()
+        .add_systems(Startup, spawn_prompt)
+        .add_systems(
+            Update,
+            (find_target, highlight_target, hold_interact, show_prompt)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, InputAction)]
+#[action_output(bool)]
+pub struct Interact;
+
+/// Something the player uses with [`Interact`] instead of picking it up
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component, Default)]
+#[type_path = "stoned"]
+pub struct Interactable {
+    /// Shown in the prompt after the binding, "Grab file"
+    pub verb: String,
+    /// Seconds [`Interact`] has to be held
+    pub hold: f32,
+}
+
+impl Interactable {
+    pub fn new(verb: impl Into<String>, hold: f32) -> Self {
+        Self {
+            verb: verb.into(),
+            hold,
+        }
+    }
+}
+
+impl Default for Interactable {
+    fn default() -> Self {
+        Self::new("Use", 0.0)
+    }
+}
+
+/// Triggered on an [`Interactable`] once [`Interact`] was held long enough
+#[derive(EntityEvent)]
+pub struct Interacted {
+    pub entity: Entity,
+}
+
+/// The prop or interactable under the crosshair
+#[derive(Resource, Default, Clone, Copy, PartialEq, Debug)]
+pub struct LookTarget(pub Option<Entity>);
+
+/// How long [`Interact`] has been held on the current target
+#[derive(Resource, Default, Debug)]
+struct InteractHold {
+    elapsed: f32,
+    /// already interacted, waits for the button to be let go
+    done: bool,
+}
+
+/// Materials swapped out while highlighted, put back once the player looks away
+#[derive(Component)]
+struct Highlighted(Vec<(Entity, Handle<StandardMaterial>)>);
+
+fn spawn_prompt(mut cmd: Commands) {
+    cmd.spawn(interact_prompt());
+}
+
+fn find_target(
+    spatial: SpatialQuery,
+    camera: Single<(&GlobalTransform, &PickupConfig), With<CharacterControllerCameraOf>>,
+    actor: Option<Single<&AvianPickupActorState>>,
+    targets: Query<(), Or<(With<DynamicProp>, With<Interactable>)>>,
+    parents: Query<&ChildOf>,
+    mut target: ResMut<LookTarget>,
+) {
+    // nothing to point out with something in hand
+    if actor.is_some_and(|actor| matches!(**actor, AvianPickupActorState::Holding(_))) {
+        target.set_if_neq(LookTarget(None));
+        return;
+    }
+
+    let (transform, config) = camera.into_inner();
+    let origin = transform.translation();
+    let direction = transform.forward();
+    // props behind walls don't count
+    let obstacle = spatial
+        .cast_ray(origin, direction, REACH, true, &config.obstacle_filter)
+        .map_or(REACH, |hit| hit.distance);
+    // whatever the pickup can grab, and the interactables it can't
+    let filter = SpatialQueryFilter {
+        mask: config.prop_filter.mask | LayerMask::from(CollisionLayer::Interactable),
+        ..config.prop_filter.clone()
+    };
+    let hit = spatial
+        .cast_ray(origin, direction, REACH, true, &filter)
+        .filter(|hit| hit.distance <= obstacle)
+        .and_then(|hit| {
+            // colliders can sit below the entity with the component
+            std::iter::once(hit.entity)
+                .chain(parents.iter_ancestors(hit.entity))
+                .find(|entity| targets.contains(*entity))
+        });
+    target.set_if_neq(LookTarget(hit));
+}
+
+fn highlight_target(
+    mut cmd: Commands,
+    target: Res<LookTarget>,
+    mut previous: Local<Option<Entity>>,
+    highlighted: Query<&Highlighted>,
+    children: Query<&Children>,
+    meshes: Query<&MeshMaterial3d<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !target.is_changed() {
+        return;
+    }
+    if let Some(old) = previous.take()
+        && let Ok(Highlighted(originals)) = highlighted.get(old)
+    {
+        for (entity, material) in originals {
+            cmd.entity(*entity)
+                .try_insert(MeshMaterial3d(material.clone()));
+        }
+        cmd.entity(old).try_remove::<Highlighted>();
+    }
+
+    let Some(new) = target.0 else {
+        return;
+    };
+    let mut originals = Vec::new();
+    for entity in std::iter::once(new).chain(children.iter_descendants(new)) {
+        let Ok(material) = meshes.get(entity) else {
+            continue;
+        };
+        let Some(mut lit) = materials.get(&material.0).cloned() else {
+            continue;
+        };
+        lit.emissive = lit.emissive + HIGHLIGHT;
+        originals.push((entity, material.0.clone()));
+        cmd.entity(entity)
+            .try_insert(MeshMaterial3d(materials.add(lit)));
+    }
+    cmd.entity(new).try_insert(Highlighted(originals));
+    *previous = Some(new);
+}
+
+fn hold_interact(
+    mut cmd: Commands,
+    time: Res<Time>,
+    target: Res<LookTarget>,
+    interact: Single<&Action<Interact>>,
+    interactables: Query<&Interactable>,
+    mut hold: ResMut<InteractHold>,
+) {
+    let interactable = target.0.and_then(|e| Some((e, interactables.get(e).ok()?)));
+    // looking away starts over
+    if !**interact || target.is_changed() {
+        *hold = InteractHold::default();
+    }
+    let Some((entity, interactable)) = interactable else {
+        return;
+    };
+    if !**interact || hold.done {
+        return;
+    }
+
+    hold.elapsed += time.delta_secs();
+    if hold.elapsed >= interactable.hold {
+        hold.done = true;
+        cmd.trigger(Interacted { entity });
+    }
+}
+
+fn show_prompt(
+    target: Res<LookTarget>,
+    settings: Res<Settings>,
+    hold: Res<InteractHold>,
+    interactables: Query<&Interactable>,
+    player: Single<&ContextActivity<PlayerInput>>,
+    prompt: Single<&mut Visibility, With<InteractPrompt>>,
+    mut text: Single<&mut Text, With<InteractPromptText>>,
+    progress: Single<&mut Visibility, (With<InteractProgress>, Without<InteractPrompt>)>,
+    mut fill: Single<&mut ProgressFill, With<InteractProgressFill>>,
+) {
+    let mut visibility = prompt.into_inner();
+    // no prompts while a dialogue or document has the input
+    let Some(target) = target.0.filter(|_| ***player) else {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    };
+    visibility.set_if_neq(Visibility::Inherited);
+
+    let controls = &settings.controls;
+    let (prompt, charge) = match interactables.get(target) {
+        Ok(interactable) => (
+            format!(
+                "[{}] {}",
+                controls.label(Control::Interact),
+                interactable.verb
+            ),
+            (interactable.hold > 0.0).then(|| hold.elapsed / interactable.hold),
+        ),
+        Err(_) => (
+            format!("[{}] Pick up", controls.label(Control::PullObject)),
+            None,
+        ),
+    };
+    if text.0 != prompt {
+        text.0 = prompt;
+    }
+
+    let mut progress = progress.into_inner();
+    match charge {
+        Some(charge) if charge > 0.0 => {
+            progress.set_if_neq(Visibility::Inherited);
+            fill.0 = charge;
+        }
+        _ => {
+            progress.set_if_neq(Visibility::Hidden);
+        }
+    }
+}
@@ -1,34 +1,97 @@
+use std::time::Duration;
+
 use bevy::{
-    core_pipeline::core_3d::graph::Node3d,
-    core_pipeline::fullscreen_material::{FullscreenMaterial, FullscreenMaterialPlugin},
+    asset::{AssetLoader, LoadContext, io::Reader},
+    core_pipeline::fullscreen_material::FullscreenMaterial,
     prelude::*,
-    render::render_graph::RenderLabel,
     render::{
         extract_component::ExtractComponent, render_graph::InternedRenderLabel,
         render_resource::ShaderType,
     },
     shader::ShaderRef,
 };
+use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    File, FileCollected, GameState, Progress, W,
+    dialogue::Dialogues,
+    k_audio::KAudio,
+    loading::Preload,
+    post::{self, PostPass},
+    settings::{Graphics, Settings},
+    ui_input::UiActivate,
+    widgets::photosensitivity_warning_screen,
+};
+
+/// The timer seconds left when the countdown curve starts
+const COUNTDOWN: Duration = Duration::from_secs(30);
+/// Added to `base_level` and `wave_frequency` at full [`KExertion`]
+const EXERTION_BASE_LEVEL: f32 = 0.2;
+const EXERTION_WAVE_FREQUENCY: f32 = 1.5;
 
-#[derive(Component, ExtractComponent, Clone, Copy, ShaderType)]
+/// Uniforms of the `k.wgsl` post process.
+/// Don't set these on the camera directly, they're rebuilt from [`KBase`] every frame.
+///
+/// The `#[reflect(@range)]` attributes are the slider ranges of the tuning panel.
+#[derive(Component, ExtractComponent, Clone, Copy, ShaderType, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Default)]
+#[serde(default)]
+#[require(SafeFlashes)]
 pub struct K {
+    /// clock of the waves, peaks and pulses, advanced on the cpu so safe mode can slow it down
+    #[serde(skip)]
     pub time: f32,
     /// scales all effects
+    #[reflect(@0.0..=1.0_f32)]
     pub intensity: f32,
-    /// 0.0 - 0.3
+    #[reflect(@0.0..=0.3_f32)]
     pub base_level: f32,
-    /// 0.3 - 3.0
+    #[reflect(@0.3..=3.0_f32)]
     pub peak_frequency: f32,
-    /// length of peaks 2.0 - 12.0
+    /// length of peaks
+    #[reflect(@2.0..=12.0_f32)]
     pub peak_sharpness: f32,
-    /// maximum intensity 0.4 - 1.0
+    /// maximum intensity
+    #[reflect(@0.4..=1.0_f32)]
     pub peak_intensity: f32,
-    /// 0.5 - 4.0
+    #[reflect(@0.5..=4.0_f32)]
     pub wave_frequency: f32,
-    /// 0.0 - 0.3
+    #[reflect(@0.0..=0.3_f32)]
     pub wave_intensity: f32,
+    /// 0.0 turns the posterization off, 1.0 is full strength
+    #[reflect(@0.0..=1.0_f32)]
+    pub posterize: f32,
+    /// scales the hue rotation and saturation pulse
+    #[reflect(@0.0..=1.0_f32)]
+    pub hue_shift: f32,
+    /// film grain, it's new noise every frame
+    #[reflect(@0.0..=1.0_f32)]
+    pub grain: f32,
+    /// clock of the spiral distortions, advanced on the cpu so it can slow down smoothly
+    #[serde(skip)]
+    pub spiral_time: f32,
+    /// [`KEffects`] bits, copied from the camera every frame
+    #[serde(skip)]
+    pub effects: u32,
+    /// loudness of the analyzed bus, copied from [`KAudio`] every frame
+    #[serde(skip)]
+    pub audio_rms: f32,
+    /// bass energy of the analyzed bus, copied from [`KAudio`] every frame
+    #[serde(skip)]
+    pub audio_low: f32,
+    /// [`K::shader_intensity`] with its rate of change limited, set by [`photosensitive_clamp`].
+    /// Negative lets the shader work it out from `time` on its own.
+    #[serde(skip)]
+    pub safe_intensity: f32,
 }
 
+const _: () = assert!(
+    size_of::<K>().is_multiple_of(16),
+    "K has to be padded to 16 bytes for webgl2"
+);
+
 impl Default for K {
     fn default() -> Self {
         Self {
@@ -40,8 +103,200 @@ impl Default for K {
             peak_intensity: 0.85,
             wave_frequency: 1.5,
             wave_intensity: 0.1,
+            posterize: 1.0,
+            hue_shift: 1.0,
+            grain: 1.0,
+            spiral_time: 0.0,
+            effects: KEffects::all().bits(),
+            audio_rms: 0.0,
+            audio_low: 0.0,
+            safe_intensity: -1.0,
+        }
+    }
+}
+
+impl K {
+    /// Port of `dynamic_intensity` in `k.wgsl`, keep the two in sync.
+    /// Slow waves on top of `base_level` with three overlapping peaks, 0.0 - 1.0
+    pub fn dynamic_intensity(&self, time: f32) -> f32 {
+        let base = self.base_level;
+        let sharpness = self.peak_sharpness;
+
+        // waves
+        let small_wave = (time * self.wave_frequency).sin() * 0.5 + 0.5;
+        let ambient = base + small_wave * self.wave_intensity;
+
+        // sharp spikes
+        let peak_phase = time * self.peak_frequency * 0.1;
+        let peak = peak_phase.sin().max(0.0).powf(sharpness);
+
+        let peak_phase2 = time * self.peak_frequency * 0.07 + 1.5;
+        let peak2 = peak_phase2.sin().max(0.0).powf(sharpness * 1.5) * 0.6;
+
+        // k-hole
+        let mega_phase = time * self.peak_frequency * 0.02;
+        let mega_peak = mega_phase.sin().max(0.0).powf(sharpness * 2.0) * 0.4;
+
+        let combined_peaks = peak.max(peak2).max(mega_peak);
+        let intensity = ambient + combined_peaks * (self.peak_intensity - base);
+        intensity.clamp(0.0, 1.0)
+    }
+
+    /// Blends every tunable field towards `other`, the clocks and flags stay as they are
+    pub fn lerp(&self, other: &K, t: f32) -> K {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        K {
+            intensity: lerp(self.intensity, other.intensity),
+            base_level: lerp(self.base_level, other.base_level),
+            peak_frequency: lerp(self.peak_frequency, other.peak_frequency),
+            peak_sharpness: lerp(self.peak_sharpness, other.peak_sharpness),
+            peak_intensity: lerp(self.peak_intensity, other.peak_intensity),
+            wave_frequency: lerp(self.wave_frequency, other.wave_frequency),
+            wave_intensity: lerp(self.wave_intensity, other.wave_intensity),
+            posterize: lerp(self.posterize, other.posterize),
+            hue_shift: lerp(self.hue_shift, other.hue_shift),
+            grain: lerp(self.grain, other.grain),
+            ..*self
         }
     }
+
+    /// What the shader drives its effects with this frame
+    pub fn shader_intensity(&self) -> f32 {
+        if self.safe_intensity >= 0.0 {
+            return self.safe_intensity;
+        }
+        self.unclamped_intensity()
+    }
+
+    fn unclamped_intensity(&self) -> f32 {
+        let audio = 1.0 + 0.3 * self.audio_rms;
+        (self.dynamic_intensity(self.time) * self.intensity * audio).clamp(0.0, 1.0)
+    }
+}
+
+bitflags! {
+    /// Effects of the `k.wgsl` post process that can be turned off one by one,
+    /// for weak gpus or because they're unpleasant to look at.
+    ///
+    /// [`FullscreenMaterial`] has no pipeline specialization,
+    /// so these are uniform flags the shader branches on instead of shader defs.
+    /// The branches are uniform across the screen so a disabled effect costs next to nothing.
+    ///
+    /// Copied from [`Graphics::k_effects`] onto every camera.
+    #[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(transparent)]
+    pub struct KEffects: u32 {
+        const WAVE = 1 << 0;
+        const STEREOGRAPHIC = 1 << 1;
+        const HOLE = 1 << 2;
+        const KALEIDOSCOPE = 1 << 3;
+        const CHROMATIC_ABERRATION = 1 << 4;
+        const COLOR_SHIFT = 1 << 5;
+        const FBM = 1 << 6;
+        const VIGNETTE = 1 << 7;
+        const GRAIN = 1 << 8;
+    }
+}
+
+impl Default for KEffects {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl KEffects {
+    /// Every single effect with its name for the settings, e.g. "chromatic aberration"
+    pub fn labeled() -> impl Iterator<Item = (String, KEffects)> {
+        Self::all()
+            .iter_names()
+            .map(|(name, effect)| (name.to_lowercase().replace('_', " "), effect))
+    }
+}
+
+/// Fastest an intensity can change in safe mode, per second
+const SAFE_MAX_RATE: f32 = 0.6;
+/// Most an intensity can change in a single frame, so a hitch can't jump either
+const SAFE_MAX_STEP: f32 = 0.02;
+/// A flash is the shader intensity turning around.
+/// At most three a second, like the usual photosensitivity guidelines ask
+const SAFE_FLASH_INTERVAL: f32 = 1.0 / 3.0;
+const SAFE_PEAK_INTENSITY: f32 = 0.5;
+/// Sharper peaks rise faster
+const SAFE_PEAK_SHARPNESS: f32 = 4.0;
+const SAFE_WAVE_FREQUENCY: f32 = 1.0;
+/// Spirals turn at a quarter of the normal speed
+const SAFE_SPIRAL_SPEED: f32 = 0.25;
+/// The clock of the waves, peaks and pulses runs at half speed
+const SAFE_TIME_SPEED: f32 = 0.5;
+
+/// Where the shader intensity last turned around, for [`photosensitive_clamp`]
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SafeFlashes {
+    rising: bool,
+    /// seconds
+    since_turn: f32,
+}
+
+impl Default for SafeFlashes {
+    fn default() -> Self {
+        // free to move right away
+        Self {
+            rising: false,
+            since_turn: SAFE_FLASH_INTERVAL,
+        }
+    }
+}
+
+/// Photosensitivity rules applied to the uniforms right before they're uploaded.
+/// `previous` is last frame's upload, `dt` the time since then.
+///
+/// Turns off posterization, hue rotation and grain, caps the peaks, slows the clocks down
+/// and limits how fast intensities move, including the one the shader derives from `time`.
+/// That one is what the screen's brightness follows, it's also held still instead of
+/// flashing back and forth more than three times a second.
+pub fn photosensitive_clamp(k: &mut K, previous: &K, flashes: &mut SafeFlashes, dt: f32) {
+    k.posterize = 0.0;
+    k.hue_shift = 0.0;
+    k.grain = 0.0;
+    k.time = previous.time + dt * SAFE_TIME_SPEED;
+    k.spiral_time = previous.spiral_time + dt * SAFE_SPIRAL_SPEED;
+    k.wave_frequency = k.wave_frequency.min(SAFE_WAVE_FREQUENCY);
+    k.peak_sharpness = k.peak_sharpness.min(SAFE_PEAK_SHARPNESS);
+    k.peak_intensity = k.peak_intensity.min(SAFE_PEAK_INTENSITY);
+
+    for (value, previous) in [
+        (&mut k.intensity, previous.intensity),
+        (&mut k.base_level, previous.base_level),
+        (&mut k.peak_intensity, previous.peak_intensity),
+        (&mut k.wave_intensity, previous.wave_intensity),
+        (&mut k.audio_rms, previous.audio_rms),
+        (&mut k.audio_low, previous.audio_low),
+    ] {
+        *value = step_towards(previous, *value, dt);
+    }
+
+    let from = previous.shader_intensity();
+    let to = step_towards(from, k.unclamped_intensity(), dt);
+    flashes.since_turn += dt;
+    let rising = to > from;
+    k.safe_intensity = if to == from || rising == flashes.rising {
+        to
+    } else if flashes.since_turn >= SAFE_FLASH_INTERVAL {
+        flashes.rising = rising;
+        flashes.since_turn = 0.0;
+        to
+    } else {
+        from
+    };
+}
+
+fn step_towards(from: f32, to: f32, dt: f32) -> f32 {
+    let max_step = (SAFE_MAX_RATE * dt).min(SAFE_MAX_STEP);
+    to.clamp(from - max_step, from + max_step)
+}
+
+impl PostPass for K {
+    const ORDER: u32 = 0;
 }
 
 impl FullscreenMaterial for K {
@@ -50,21 +305,799 @@ impl FullscreenMaterial for K {
     }
 
     fn node_edges() -> Vec<InternedRenderLabel> {
-        vec![
-            Node3d::Tonemapping.intern(),
-            Self::node_label().intern(),
-            Node3d::EndMainPassPostProcessing.intern(),
-        ]
+        post::chain_edges::<Self>()
     }
 }
 
 pub(super) fn plugin(app: &mut App) {
-    app.add_plugins(FullscreenMaterialPlugin::<K>::default())
-        .add_systems(Update, update_shader);
+    app.init_asset::<KEffectCurve>()
+        .init_asset_loader::<KEffectCurveLoader>()
+        .init_asset::<KPreset>()
+        .init_asset_loader::<KPresetLoader>()
+        .add_systems(Startup, (load_curves, load_presets))
+        .add_systems(OnEnter(GameState::Playing), photosensitivity_warning)
+        .init_resource::<KIntensity>()
+        .init_resource::<KExertion>()
+        .add_systems(
+            Update,
+            (
+                apply_look,
+                apply_transitions,
+                apply_effect_settings,
+                update_shader,
+                update_intensity,
+            )
+                .chain(),
+        )
+        .add_systems(
+            Update,
+            countdown_effect.run_if(in_state(GameState::Playing)),
+        )
+        .add_observer(on_file_effect)
+        .add_observer(on_siren_effect);
+}
+
+/// The intensity the player's K post process is at, in sync with the visuals.
+/// For anything that should react to the trip, like muffling audio at peaks.
+#[derive(Resource, Clone, Copy, Default, Debug, Deref)]
+pub struct KIntensity(pub f32);
+
+impl KIntensity {
+    /// How far into the hole the shader is, 0.0 - 1.0, the same threshold as `k.wgsl`
+    pub fn hole(&self) -> f32 {
+        smoothstep(0.65, 0.85, self.0)
+    }
+}
+
+/// How out of breath the player is, 0.0 - 1.0. The trip gets stronger and faster with it
+#[derive(Resource, Clone, Copy, Default, Debug, Deref, DerefMut)]
+pub struct KExertion(pub f32);
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn update_intensity(k: Single<&K, With<Camera>>, mut intensity: ResMut<KIntensity>) {
+    intensity.0 = k.shader_intensity();
+}
+
+/// The authored look of a camera, [`KEffect`]s are added on top of it
+#[derive(Component, Reflect, Clone, Copy, Default, Deref, DerefMut)]
+#[reflect(Component)]
+#[require(K, KEffects)]
+pub struct KBase(pub K);
+
+/// A named look for [`KBase`], loaded from `.kfx.ron`.
+/// Fields that are left out keep their [`K::default`] value.
+#[derive(Asset, TypePath, Deserialize, Clone, Copy, Default, Deref)]
+#[serde(transparent)]
+pub struct KPreset(pub K);
+
+/// Moves a camera's [`KBase`] to a new preset, fading the old one out on a [`KTransitionLayer`].
+/// The base is only set once, so edits from the tuning window aren't overwritten.
+/// It stays on the camera once it's done, so hot reloading `to` keeps showing up.
+#[derive(Component)]
+#[require(KBase, KTransitionLayer)]
+pub struct KTransition {
+    pub from: Handle<KPreset>,
+    pub to: Handle<KPreset>,
+    /// seconds
+    pub duration: f32,
+    pub elapsed: f32,
+    /// `to` was copied into the base
+    arrived: bool,
+}
+
+impl KTransition {
+    pub fn new(from: Handle<KPreset>, to: Handle<KPreset>, duration: f32) -> Self {
+        Self {
+            from,
+            to,
+            duration,
+            elapsed: 0.0,
+            arrived: false,
+        }
+    }
+
+    /// Starts blending from the current target to a new one
+    pub fn switch(&mut self, to: Handle<KPreset>, duration: f32) {
+        *self = Self::new(std::mem::replace(&mut self.to, to.clone()), to, duration);
+    }
+
+    fn progress(&self) -> f32 {
+        if self.duration > 0.0 {
+            smoothstep(0.0, self.duration, self.elapsed)
+        } else {
+            1.0
+        }
+    }
+}
+
+/// What the camera shows instead of its [`KBase`] while a [`KTransition`] runs
+#[derive(Component, Clone, Copy, Default)]
+pub struct KTransitionLayer(Option<K>);
+
+/// Plays a [`KEffectCurve`] on every [`K`] camera until it ends
+#[derive(Component)]
+pub struct KEffect {
+    pub curve: Handle<KEffectCurve>,
+    pub elapsed: f32,
+}
+
+impl KEffect {
+    pub fn new(curve: Handle<KEffectCurve>) -> Self {
+        Self {
+            curve,
+            elapsed: 0.0,
+        }
+    }
+}
+
+/// Keyframed offsets for every [`K`] field, loaded from `.kcurve.ron`.
+/// Tracks that are left out don't touch their field.
+#[derive(Asset, TypePath, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct KEffectCurve {
+    /// start over at the end instead of finishing
+    pub looping: bool,
+    pub intensity: Track,
+    pub base_level: Track,
+    pub peak_frequency: Track,
+    pub peak_sharpness: Track,
+    pub peak_intensity: Track,
+    pub wave_frequency: Track,
+    pub wave_intensity: Track,
 }
 
-fn update_shader(time: Res<Time>, mut query: Query<&mut K>) {
-    for mut effect in query.iter_mut() {
-        effect.time = time.elapsed_secs();
+impl KEffectCurve {
+    fn tracks(&self) -> [&Track; 7] {
+        [
+            &self.intensity,
+            &self.base_level,
+            &self.peak_frequency,
+            &self.peak_sharpness,
+            &self.peak_intensity,
+            &self.wave_frequency,
+            &self.wave_intensity,
+        ]
+    }
+
+    /// Time of the last keyframe over all tracks
+    pub fn duration(&self) -> f32 {
+        self.tracks().iter().map(|t| t.end()).fold(0.0, f32::max)
+    }
+
+    /// Adds the offsets at `t` seconds into the curve
+    pub fn apply(&self, t: f32, k: &mut K) {
+        k.intensity += self.intensity.sample(t);
+        k.base_level += self.base_level.sample(t);
+        k.peak_frequency += self.peak_frequency.sample(t);
+        k.peak_sharpness += self.peak_sharpness.sample(t);
+        k.peak_intensity += self.peak_intensity.sample(t);
+        k.wave_frequency += self.wave_frequency.sample(t);
+        k.wave_intensity += self.wave_intensity.sample(t);
+    }
+}
+
+/// `(time, value)` keyframes sorted by time, linearly interpolated
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(transparent)]
+pub struct Track(pub Vec<(f32, f32)>);
+
+impl Track {
+    pub fn sample(&self, t: f32) -> f32 {
+        let Some(&(start, first)) = self.0.first() else {
+            return 0.0;
+        };
+        if t <= start {
+            return first;
+        }
+        for pair in self.0.windows(2) {
+            let [(t0, v0), (t1, v1)] = [pair[0], pair[1]];
+            if t <= t1 {
+                let s = if t1 > t0 { (t - t0) / (t1 - t0) } else { 1.0 };
+                return v0 + (v1 - v0) * s;
+            }
+        }
+        self.0.last().map_or(0.0, |k| k.1)
+    }
+
+    pub fn end(&self) -> f32 {
+        self.0.last().map_or(0.0, |k| k.0)
+    }
+}
+
+#[derive(Default, TypePath)]
+struct KEffectCurveLoader;
+
+/// Possible errors that can be produced by [`KEffectCurveLoader`]
+#[non_exhaustive]
+#[derive(Debug, Error)]
+enum KEffectCurveLoaderError {
+    /// An [IO](std::io) Error
+    #[error("Could not load file: {0}")]
+    Io(#[from] std::io::Error),
+    /// A [RON](ron) Error
+    #[error("Could not parse RON: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for KEffectCurveLoader {
+    type Asset = KEffectCurve;
+    type Settings = ();
+    type Error = KEffectCurveLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _ctx: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["kcurve.ron"]
+    }
+}
+
+#[derive(Default, TypePath)]
+struct KPresetLoader;
+
+/// Possible errors that can be produced by [`KPresetLoader`]
+#[non_exhaustive]
+#[derive(Debug, Error)]
+enum KPresetLoaderError {
+    /// An [IO](std::io) Error
+    #[error("Could not load file: {0}")]
+    Io(#[from] std::io::Error),
+    /// A [RON](ron) Error
+    #[error("Could not parse RON: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for KPresetLoader {
+    type Asset = KPreset;
+    type Settings = ();
+    type Error = KPresetLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _ctx: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["kfx.ron"]
+    }
+}
+
+/// Seconds to blend to a look picked in the settings
+const LOOK_BLEND: f32 = 3.0;
+
+/// How strong the trip is, picked in the settings like a difficulty.
+/// Each one is a `.kfx.ron` preset in `assets/k`, mild is the original look
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KLook {
+    Sober,
+    #[default]
+    Mild,
+    Peaking,
+    KHole,
+}
+
+impl KLook {
+    pub fn label(self) -> &'static str {
+        match self {
+            KLook::Sober => "sober",
+            KLook::Mild => "mild",
+            KLook::Peaking => "peaking",
+            KLook::KHole => "k-hole",
+        }
+    }
+
+    /// The one after this on the settings screen, wrapping around
+    pub fn next(self) -> Self {
+        match self {
+            KLook::Sober => KLook::Mild,
+            KLook::Mild => KLook::Peaking,
+            KLook::Peaking => KLook::KHole,
+            KLook::KHole => KLook::Sober,
+        }
+    }
+}
+
+/// A preset for every [`KLook`]
+#[derive(Resource)]
+pub struct KPresets {
+    pub sober: Handle<KPreset>,
+    pub mild: Handle<KPreset>,
+    pub peaking: Handle<KPreset>,
+    pub khole: Handle<KPreset>,
+}
+
+impl KPresets {
+    pub fn get(&self, look: KLook) -> &Handle<KPreset> {
+        match look {
+            KLook::Sober => &self.sober,
+            KLook::Mild => &self.mild,
+            KLook::Peaking => &self.peaking,
+            KLook::KHole => &self.khole,
+        }
+    }
+}
+
+fn load_presets(mut cmd: Commands, ass: Res<AssetServer>, mut preload: ResMut<Preload>) {
+    let presets = KPresets {
+        sober: ass.load("k/sober.kfx.ron"),
+        mild: ass.load("k/mild.kfx.ron"),
+        peaking: ass.load("k/peaking.kfx.ron"),
+        khole: ass.load("k/k-hole.kfx.ron"),
+    };
+    for preset in [
+        &presets.sober,
+        &presets.mild,
+        &presets.peaking,
+        &presets.khole,
+    ] {
+        preload.add(preset.clone());
+    }
+    cmd.insert_resource(presets);
+}
+
+/// Starts the cameras on the look from the settings and blends over when it's changed
+fn apply_look(
+    mut cmd: Commands,
+    settings: Res<Settings>,
+    presets: Res<KPresets>,
+    mut cameras: Query<(Entity, Option<&mut KTransition>), With<KBase>>,
+) {
+    let to = presets.get(settings.graphics.k_look);
+    for (camera, transition) in &mut cameras {
+        match transition {
+            Some(mut transition) if transition.to != *to => {
+                transition.switch(to.clone(), LOOK_BLEND);
+            }
+            Some(_) => {}
+            None => {
+                cmd.entity(camera)
+                    .insert(KTransition::new(to.clone(), to.clone(), 0.0));
+            }
+        }
+    }
+}
+
+fn apply_transitions(
+    time: Res<Time>,
+    presets: Res<Assets<KPreset>>,
+    mut events: MessageReader<AssetEvent<KPreset>>,
+    mut cameras: Query<(&mut KTransition, &mut KBase, &mut KTransitionLayer)>,
+) {
+    let modified: Vec<_> = events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (mut transition, mut base, mut layer) in &mut cameras {
+        // still loading
+        let (Some(from), Some(to)) = (presets.get(&transition.from), presets.get(&transition.to))
+        else {
+            continue;
+        };
+        if !transition.arrived || modified.contains(&transition.to.id()) {
+            transition.arrived = true;
+            base.0 = to.0;
+        }
+        // blends towards the base, so tuning it shows up right away
+        layer.0 = (transition.elapsed <= transition.duration).then(|| {
+            transition.elapsed += time.delta_secs();
+            from.lerp(&base.0, transition.progress())
+        });
+    }
+}
+
+/// The curves gameplay moments play
+#[derive(Resource)]
+struct KCurves {
+    khole: Handle<KEffectCurve>,
+    acid: Handle<KEffectCurve>,
+    siren: Handle<KEffectCurve>,
+    countdown: Handle<KEffectCurve>,
+}
+
+fn load_curves(mut cmd: Commands, ass: Res<AssetServer>, mut preload: ResMut<Preload>) {
+    let curves = KCurves {
+        khole: ass.load("k/khole.kcurve.ron"),
+        acid: ass.load("k/acid.kcurve.ron"),
+        siren: ass.load("k/siren.kcurve.ron"),
+        countdown: ass.load("k/countdown.kcurve.ron"),
+    };
+    for curve in [
+        &curves.khole,
+        &curves.acid,
+        &curves.siren,
+        &curves.countdown,
+    ] {
+        preload.add(curve.clone());
+    }
+    cmd.insert_resource(curves);
+}
+
+fn on_file_effect(
+    on: On<FileCollected>,
+    mut cmd: Commands,
+    files: Query<&File>,
+    curves: Res<KCurves>,
+) {
+    let Ok(file) = files.get(on.file) else {
+        return;
+    };
+    let curve = match file.dialogue {
+        Dialogues::Khole => &curves.khole,
+        Dialogues::Acid => &curves.acid,
+        _ => return,
+    };
+    cmd.spawn((Name::new("k effect"), KEffect::new(curve.clone())));
+}
+
+/// The looping siren effect, there's only ever one
+#[derive(Component)]
+struct KSiren;
+
+fn on_siren_effect(
+    _: On<W>,
+    mut cmd: Commands,
+    curves: Res<KCurves>,
+    sirens: Query<Entity, With<KSiren>>,
+) {
+    for siren in &sirens {
+        cmd.entity(siren).despawn();
+    }
+    cmd.spawn((
+        Name::new("k siren"),
+        KSiren,
+        KEffect::new(curves.siren.clone()),
+    ));
+}
+
+fn countdown_effect(
+    mut cmd: Commands,
+    prog: Res<Progress>,
+    curves: Res<KCurves>,
+    mut started: Local<bool>,
+) {
+    if !*started && !prog.timer.is_finished() && prog.timer.remaining() <= COUNTDOWN {
+        *started = true;
+        cmd.spawn((
+            Name::new("k countdown"),
+            KEffect::new(curves.countdown.clone()),
+        ));
+    }
+}
+
+fn apply_effect_settings(settings: Res<Settings>, mut cameras: Query<&mut KEffects>) {
+    for mut effects in &mut cameras {
+        effects.set_if_neq(settings.graphics.k_effects);
+    }
+}
+
+fn update_shader(
+    mut cmd: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    audio: Res<KAudio>,
+    exertion: Res<KExertion>,
+    curves: Res<Assets<KEffectCurve>>,
+    mut effects: Query<(Entity, &mut KEffect)>,
+    mut query: Query<(
+        &mut K,
+        &mut SafeFlashes,
+        &KBase,
+        Option<&KTransitionLayer>,
+        &KEffects,
+    )>,
+) {
+    let mut playing = Vec::new();
+    for (entity, mut effect) in &mut effects {
+        // still loading
+        let Some(curve) = curves.get(&effect.curve) else {
+            continue;
+        };
+        effect.elapsed += time.delta_secs();
+        let duration = curve.duration();
+        if effect.elapsed > duration {
+            if curve.looping && duration > 0.0 {
+                effect.elapsed %= duration;
+            } else {
+                cmd.entity(entity).despawn();
+                continue;
+            }
+        }
+        playing.push((curve, effect.elapsed));
+    }
+
+    let dt = time.delta_secs();
+    for (mut k, mut flashes, base, layer, enabled) in &mut query {
+        let previous = *k;
+        let mut target = layer.and_then(|layer| layer.0).unwrap_or(base.0);
+        target.time = previous.time + dt;
+        target.effects = enabled.bits();
+        target.audio_rms = audio.rms;
+        target.audio_low = audio.low;
+        target.spiral_time = previous.spiral_time + dt;
+        for (curve, elapsed) in &playing {
+            curve.apply(*elapsed, &mut target);
+        }
+        target.base_level += exertion.0 * EXERTION_BASE_LEVEL;
+        target.wave_frequency += exertion.0 * EXERTION_WAVE_FREQUENCY;
+        if settings.accessibility.photosensitive_safe {
+            photosensitive_clamp(&mut target, &previous, &mut flashes, dt);
+        }
+        *k = target;
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+pub enum WarningChoice {
+    SafeMode,
+    Continue,
+}
+
+fn photosensitivity_warning(mut cmd: Commands, settings: Res<Settings>) {
+    if !settings.accessibility.warning_seen {
+        cmd.spawn(photosensitivity_warning_screen())
+            .observe(on_warning_choice);
+    }
+}
+
+fn on_warning_choice(
+    on: On<UiActivate>,
+    mut cmd: Commands,
+    choices: Query<&WarningChoice>,
+    mut settings: ResMut<Settings>,
+) {
+    let Ok(choice) = choices.get(on.original_event_target()) else {
+        return;
+    };
+    settings.accessibility.warning_seen = true;
+    settings.accessibility.photosensitive_safe = matches!(choice, WarningChoice::SafeMode);
+    cmd.entity(on.entity).despawn();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME_RATES: [f32; 4] = [30.0, 60.0, 144.0, 240.0];
+
+    /// Every frame the uniforms want to be `to`, starting out at `from`
+    fn run_safe(fps: f32, seconds: f32, from: K, to: K) -> Vec<K> {
+        run_safe_with(fps, seconds, from, |_| to)
+    }
+
+    /// Every frame the uniforms want to be `to(frame)`, starting out at `from`
+    fn run_safe_with(fps: f32, seconds: f32, from: K, to: impl Fn(usize) -> K) -> Vec<K> {
+        let dt = 1.0 / fps;
+        let mut previous = from;
+        let mut flashes = SafeFlashes::default();
+        (0..(fps * seconds).round() as usize)
+            .map(|frame| {
+                let mut k = to(frame);
+                k.time = previous.time + dt;
+                k.spiral_time = previous.spiral_time + dt;
+                photosensitive_clamp(&mut k, &previous, &mut flashes, dt);
+                previous = k;
+                k
+            })
+            .collect()
+    }
+
+    /// How often the shader intensity turns around
+    fn turns(frames: &[K]) -> usize {
+        let steps: Vec<_> = frames
+            .windows(2)
+            .map(|pair| pair[1].shader_intensity() - pair[0].shader_intensity())
+            .filter(|step| *step != 0.0)
+            .collect();
+        steps
+            .windows(2)
+            .filter(|pair| pair[0].signum() != pair[1].signum())
+            .count()
+    }
+
+    fn off() -> K {
+        K {
+            intensity: 0.0,
+            ..default()
+        }
+    }
+
+    /// No waves, so only the peaks move the intensity
+    fn calm() -> K {
+        K {
+            wave_intensity: 0.0,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn intensity_at_known_times() {
+        let k = K::default();
+        // the waves are halfway up, the second peak is close to its top
+        let peak2 = 1.5_f32.sin().powf(9.0) * 0.6;
+        let expected = 0.05 + 0.5 * 0.1 + peak2 * (0.85 - 0.05);
+        assert!((k.dynamic_intensity(0.0) - expected).abs() < 1e-5);
+
+        // top of the first peak
+        let top = 5.0 * std::f32::consts::PI;
+        assert!((calm().dynamic_intensity(top) - 0.85).abs() < 1e-5);
+
+        // all three peaks are below zero here, only the base is left
+        assert!((calm().dynamic_intensity(230.0) - 0.05).abs() < 1e-5);
+    }
+
+    #[test]
+    fn intensity_stays_between_base_and_peak() {
+        let k = calm();
+        for i in 0..20_000 {
+            let intensity = k.dynamic_intensity(i as f32 * 0.05);
+            assert!(
+                (k.base_level - 1e-5..=k.peak_intensity + 1e-5).contains(&intensity),
+                "{intensity} at {i}"
+            );
+        }
+    }
+
+    #[test]
+    fn intensity_is_clamped() {
+        let extreme = [
+            K {
+                base_level: 0.3,
+                peak_intensity: 1.0,
+                wave_intensity: 0.3,
+                ..default()
+            },
+            K {
+                base_level: -0.5,
+                peak_intensity: -1.0,
+                ..default()
+            },
+        ];
+        for k in extreme {
+            for i in 0..20_000 {
+                let intensity = k.dynamic_intensity(i as f32 * 0.05);
+                assert!((0.0..=1.0).contains(&intensity), "{intensity} at {i}");
+            }
+        }
+    }
+
+    /// Float literals in the body of `fn dynamic_intensity` in `source`
+    fn dynamic_intensity_literals(source: &str) -> Vec<f32> {
+        let start = source.find("fn dynamic_intensity(").unwrap();
+        let body = &source[start..];
+        // the end of the wgsl function, or of the method in `impl K`
+        let end = ["\n}", "\n    }"]
+            .iter()
+            .filter_map(|end| body.find(end))
+            .min()
+            .unwrap();
+        let body = &body[..end];
+        body.split(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '.')
+            .filter(|token| token.contains('.'))
+            .filter_map(|token| token.parse().ok())
+            .collect()
+    }
+
+    #[test]
+    fn intensity_matches_the_shader() {
+        let shader = dynamic_intensity_literals(include_str!("../assets/k.wgsl"));
+        let port = dynamic_intensity_literals(include_str!("k.rs"));
+        assert!(!shader.is_empty());
+        assert_eq!(
+            shader, port,
+            "K::dynamic_intensity and k.wgsl went out of sync"
+        );
+    }
+
+    #[test]
+    fn safe_rate_is_per_second() {
+        for fps in FRAME_RATES {
+            let frames = run_safe(fps, 1.0, off(), K::default());
+            let last = frames.last().unwrap();
+            assert!(
+                (last.intensity - SAFE_MAX_RATE).abs() < 1e-3,
+                "{fps} fps got to {}",
+                last.intensity
+            );
+        }
+    }
+
+    #[test]
+    fn safe_intensity_never_jumps() {
+        // fast and sharp peaks, the time driven intensity swings around a lot
+        let wild = K {
+            peak_frequency: 3.0,
+            wave_frequency: 4.0,
+            wave_intensity: 0.3,
+            ..default()
+        };
+        for fps in FRAME_RATES {
+            let max_step = SAFE_MAX_RATE / fps + 1e-5;
+            let frames = run_safe(fps, 30.0, K::default(), wild);
+            for pair in frames.windows(2) {
+                let step = pair[1].shader_intensity() - pair[0].shader_intensity();
+                assert!(step.abs() <= max_step, "{fps} fps stepped {step}");
+            }
+        }
+    }
+
+    #[test]
+    fn safe_mode_continues_from_the_unclamped_intensity() {
+        let sober = K {
+            time: 10.0,
+            ..default()
+        };
+        let frames = run_safe(60.0, 1.0 / 60.0, sober, sober);
+        let step = frames[0].shader_intensity() - sober.shader_intensity();
+        assert!(step.abs() <= SAFE_MAX_RATE / 60.0 + 1e-5);
+    }
+
+    #[test]
+    fn safe_clocks_slow_down() {
+        for fps in FRAME_RATES {
+            let frames = run_safe(fps, 2.0, K::default(), K::default());
+            let last = frames.last().unwrap();
+            assert!((last.time - 2.0 * SAFE_TIME_SPEED).abs() < 1e-3);
+            assert!((last.spiral_time - 2.0 * SAFE_SPIRAL_SPEED).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn safe_mode_stops_a_strobe() {
+        // a bright flash every other frame
+        let strobe = |frame: usize| {
+            if frame.is_multiple_of(2) {
+                off()
+            } else {
+                calm()
+            }
+        };
+        for fps in FRAME_RATES {
+            let seconds = 3.0;
+            let frames = run_safe_with(fps, seconds, off(), strobe);
+            let flashes = turns(&frames);
+            assert!(
+                flashes as f32 <= seconds * 3.0 + 1.0,
+                "{fps} fps flashed {flashes} times"
+            );
+            for pair in frames.windows(2) {
+                let step = pair[1].shader_intensity() - pair[0].shader_intensity();
+                assert!(
+                    step.abs() <= SAFE_MAX_STEP + 1e-5,
+                    "{fps} fps stepped {step}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn safe_mode_caps_a_long_frame() {
+        let frames = run_safe(2.0, 1.0, off(), K::default());
+        let step = frames[1].intensity - frames[0].intensity;
+        assert!(
+            step <= SAFE_MAX_STEP + 1e-5,
+            "a half second frame stepped {step}"
+        );
     }
 }
@@ -0,0 +1,291 @@
+//! Audio analysis feeding the K post process, so the distortion breathes with what's playing.
+//!
+//! A [`KAnalyzerNode`] sits in the bevy_seedling graph as a sink. The main bus is connected
+//! to it by default, connect any other bus to the [`KAnalyzer`] entity to follow that instead.
+//! The dsp lives in [`Analyzer`], which doesn't know about the audio graph,
+//! so it can be fed offline buffers.
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, Ordering},
+};
+
+use bevy::prelude::*;
+use bevy_seedling::{
+    firewheel::{
+        channel_config::{ChannelConfig, ChannelCount},
+        diff::{Diff, Patch},
+        event::ProcEvents,
+        node::{
+            AudioNode, AudioNodeInfo, AudioNodeProcessor, ConstructProcessorContext, EmptyConfig,
+            ProcBuffers, ProcExtra, ProcInfo, ProcessStatus,
+        },
+    },
+    prelude::*,
+};
+
+/// Upper edge of the low band
+const LOW_CUTOFF: f32 = 200.0;
+/// Seconds for the levels to rise and fall, the shader shouldn't twitch on every transient
+const ATTACK: f32 = 0.03;
+const RELEASE: f32 = 0.25;
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_simple_node::<KAnalyzerNode>()
+        .init_resource::<KAudio>()
+        .add_observer(analyze_main_bus)
+        .add_systems(Update, read_levels);
+}
+
+/// Smoothed levels of the analyzed bus, roughly 0.0 - 1.0
+#[derive(Resource, Clone, Copy, Default, Debug, PartialEq)]
+pub struct KAudio {
+    pub rms: f32,
+    /// energy below [`LOW_CUTOFF`]
+    pub low: f32,
+}
+
+/// The entity the analyzed bus is connected to
+#[derive(Component)]
+pub struct KAnalyzer;
+
+/// Written on the audio thread, read once a frame
+#[derive(Default, Debug)]
+pub struct SharedLevels {
+    rms: AtomicU32,
+    low: AtomicU32,
+}
+
+impl SharedLevels {
+    fn store(&self, levels: KAudio) {
+        self.rms.store(levels.rms.to_bits(), Ordering::Relaxed);
+        self.low.store(levels.low.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn load(&self) -> KAudio {
+        KAudio {
+            rms: f32::from_bits(self.rms.load(Ordering::Relaxed)),
+            low: f32::from_bits(self.low.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Band split and envelope followers, one block at a time
+#[derive(Clone, Debug)]
+pub struct Analyzer {
+    sample_rate: f32,
+    /// one pole lowpass state per channel
+    low_pass: Vec<f32>,
+    levels: KAudio,
+}
+
+impl Analyzer {
+    pub fn new(sample_rate: f32, channels: usize) -> Self {
+        Self {
+            sample_rate,
+            low_pass: vec![0.0; channels],
+            levels: KAudio::default(),
+        }
+    }
+
+    pub fn levels(&self) -> KAudio {
+        self.levels
+    }
+
+    /// Analyzes one block, every channel holds the same number of frames
+    pub fn process(&mut self, channels: &[&[f32]], gain: f32) -> KAudio {
+        let frames = channels.first().map_or(0, |c| c.len());
+        if frames == 0 {
+            return self.levels;
+        }
+
+        let low_coeff = one_pole(LOW_CUTOFF, self.sample_rate);
+        let mut sum = KAudio::default();
+        for (i, channel) in channels.iter().enumerate().take(self.low_pass.len()) {
+            for &sample in *channel {
+                let sample = sample * gain;
+                self.low_pass[i] += (sample - self.low_pass[i]) * low_coeff;
+                let low = self.low_pass[i];
+                sum.rms += sample * sample;
+                sum.low += low * low;
+            }
+        }
+
+        let count = (frames * channels.len().min(self.low_pass.len())) as f32;
+        let block = frames as f32 / self.sample_rate;
+        let follow = |level: f32, square_sum: f32| {
+            let target = (square_sum / count).sqrt().min(1.0);
+            let time = if target > level { ATTACK } else { RELEASE };
+            level + (target - level) * (1.0 - (-block / time).exp())
+        };
+        self.levels = KAudio {
+            rms: follow(self.levels.rms, sum.rms),
+            low: follow(self.levels.low, sum.low),
+        };
+        self.levels
+    }
+}
+
+fn one_pole(cutoff: f32, sample_rate: f32) -> f32 {
+    1.0 - (-std::f32::consts::TAU * cutoff / sample_rate).exp()
+}
+
+/// Sink node running an [`Analyzer`] over its stereo input
+#[derive(Diff, Patch, Component, Clone, Debug)]
+pub struct KAnalyzerNode {
+    /// applied before analysis, for quiet mixes
+    pub gain: f32,
+    #[diff(skip)]
+    pub levels: Arc<SharedLevels>,
+}
+
+impl Default for KAnalyzerNode {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            levels: default(),
+        }
+    }
+}
+
+impl AudioNode for KAnalyzerNode {
+    type Configuration = EmptyConfig;
+
+    fn info(&self, _config: &Self::Configuration) -> AudioNodeInfo {
+        AudioNodeInfo::new()
+            .debug_name("k_analyzer")
+            .channel_config(ChannelConfig {
+                num_inputs: ChannelCount::STEREO,
+                num_outputs: ChannelCount::ZERO,
+            })
+    }
+
+    fn construct_processor(
+        &self,
+        _config: &Self::Configuration,
+        cx: ConstructProcessorContext,
+    ) -> impl AudioNodeProcessor {
+        KAnalyzerProcessor {
+            params: self.clone(),
+            analyzer: Analyzer::new(cx.stream_info.sample_rate.get() as f32, 2),
+        }
+    }
+}
+
+struct KAnalyzerProcessor {
+    params: KAnalyzerNode,
+    analyzer: Analyzer,
+}
+
+impl AudioNodeProcessor for KAnalyzerProcessor {
+    fn process(
+        &mut self,
+        info: &ProcInfo,
+        ProcBuffers { inputs, .. }: ProcBuffers,
+        events: &mut ProcEvents,
+        _extra: &mut ProcExtra,
+    ) -> ProcessStatus {
+        for patch in events.drain_patches::<KAnalyzerNode>() {
+            self.params.apply(patch);
+        }
+
+        let frames = info.frames;
+        let channels = [&inputs[0][..frames], &inputs[1][..frames]];
+        let levels = self.analyzer.process(&channels, self.params.gain);
+        self.params.levels.store(levels);
+        ProcessStatus::ClearAllOutputs
+    }
+}
+
+fn analyze_main_bus(on: On<Add, MainBus>, mut cmd: Commands) {
+    let analyzer = cmd
+        .spawn((Name::new("k analyzer"), KAnalyzer, KAnalyzerNode::default()))
+        .id();
+    cmd.entity(on.entity).connect(analyzer);
+}
+
+fn read_levels(nodes: Query<&KAnalyzerNode, With<KAnalyzer>>, mut audio: ResMut<KAudio>) {
+    let Some(node) = nodes.iter().next() else {
+        return;
+    };
+    audio.set_if_neq(node.levels.load());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.0;
+    const BLOCK: usize = 512;
+
+    /// Feeds `seconds` of a full scale stereo sine, block by block
+    fn sine(analyzer: &mut Analyzer, frequency: f32, seconds: f32) -> KAudio {
+        let frames = (seconds * SAMPLE_RATE) as usize;
+        let signal: Vec<f32> = (0..frames)
+            .map(|i| (std::f32::consts::TAU * frequency * i as f32 / SAMPLE_RATE).sin())
+            .collect();
+        for block in signal.chunks(BLOCK) {
+            analyzer.process(&[block, block], 1.0);
+        }
+        analyzer.levels()
+    }
+
+    fn silence(analyzer: &mut Analyzer, seconds: f32) -> KAudio {
+        let block = [0.0; BLOCK];
+        for _ in 0..(seconds * SAMPLE_RATE) as usize / BLOCK {
+            analyzer.process(&[&block, &block], 1.0);
+        }
+        analyzer.levels()
+    }
+
+    #[test]
+    fn silence_stays_silent() {
+        let mut analyzer = Analyzer::new(SAMPLE_RATE, 2);
+        assert_eq!(silence(&mut analyzer, 1.0), KAudio::default());
+    }
+
+    #[test]
+    fn empty_block_keeps_the_levels() {
+        let mut analyzer = Analyzer::new(SAMPLE_RATE, 2);
+        let levels = sine(&mut analyzer, 440.0, 0.5);
+        assert_eq!(analyzer.process(&[&[], &[]], 1.0), levels);
+        assert_eq!(analyzer.process(&[], 1.0), levels);
+    }
+
+    #[test]
+    fn bass_is_loud_and_low() {
+        let mut analyzer = Analyzer::new(SAMPLE_RATE, 2);
+        let levels = sine(&mut analyzer, 50.0, 1.0);
+        // a sine's rms is 1/sqrt(2), it wobbles a bit with blocks shorter than a period
+        assert!((levels.rms - 0.707).abs() < 0.05, "{levels:?}");
+        assert!(levels.low > 0.6, "{levels:?}");
+    }
+
+    #[test]
+    fn treble_is_loud_but_not_low() {
+        let mut analyzer = Analyzer::new(SAMPLE_RATE, 2);
+        let levels = sine(&mut analyzer, 5000.0, 1.0);
+        assert!((levels.rms - 0.707).abs() < 0.02, "{levels:?}");
+        assert!(levels.low < 0.05, "{levels:?}");
+    }
+
+    #[test]
+    fn gain_scales_the_levels() {
+        let mut analyzer = Analyzer::new(SAMPLE_RATE, 1);
+        let block = [0.5; BLOCK];
+        for _ in 0..100 {
+            analyzer.process(&[&block], 0.5);
+        }
+        assert!((analyzer.levels().rms - 0.25).abs() < 0.01);
+    }
+
+    #[test]
+    fn levels_fall_slower_than_they_rise() {
+        let mut analyzer = Analyzer::new(SAMPLE_RATE, 2);
+        let risen = sine(&mut analyzer, 50.0, ATTACK).rms;
+        let loud = sine(&mut analyzer, 50.0, 1.0).rms;
+        let fallen = loud - silence(&mut analyzer, ATTACK).rms;
+        assert!(fallen < risen, "rose {risen}, fell {fallen}");
+        assert!(silence(&mut analyzer, 2.0).rms < 0.01);
+    }
+}
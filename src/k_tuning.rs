@@ -0,0 +1,140 @@
+//! Egui window for tuning the player's [`KBase`] live, with presets saved next to the other K assets.
+
+use std::{collections::VecDeque, ops::RangeInclusive};
+
+use bevy::{
+    prelude::*,
+    reflect::{GetField, Typed},
+};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+
+use crate::k::{K, KBase, KIntensity};
+
+/// Presets are written into the source assets, so they only work natively
+#[cfg(not(target_arch = "wasm32"))]
+const PRESETS: &str = "assets/k";
+/// Frames of history in the intensity graph
+const GRAPH_SAMPLES: usize = 300;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<KTuning>()
+        .add_systems(EguiPrimaryContextPass, tuning_window);
+}
+
+#[derive(Resource)]
+struct KTuning {
+    history: VecDeque<f32>,
+    preset: String,
+    status: String,
+}
+
+impl Default for KTuning {
+    fn default() -> Self {
+        Self {
+            history: VecDeque::with_capacity(GRAPH_SAMPLES),
+            preset: "mild".into(),
+            status: String::new(),
+        }
+    }
+}
+
+fn tuning_window(
+    mut contexts: EguiContexts,
+    mut tuning: ResMut<KTuning>,
+    intensity: Res<KIntensity>,
+    mut base: Single<&mut KBase>,
+) -> Result {
+    if tuning.history.len() == GRAPH_SAMPLES {
+        tuning.history.pop_front();
+    }
+    tuning.history.push_back(**intensity);
+
+    let ctx = contexts.ctx_mut()?;
+    egui::Window::new("K").default_open(false).show(ctx, |ui| {
+        intensity_graph(ui, &tuning.history);
+        ui.label(format!(
+            "intensity {:.2}, hole {:.2}",
+            **intensity,
+            intensity.hole()
+        ));
+        ui.separator();
+
+        let info = K::type_info().as_struct().expect("K is a struct");
+        for field in info.iter() {
+            // fields without a range aren't meant to be tuned
+            let Some(range) = field.get_attribute::<RangeInclusive<f32>>() else {
+                continue;
+            };
+            if let Some(value) = base.0.get_field_mut::<f32>(field.name()) {
+                ui.add(egui::Slider::new(value, range.clone()).text(field.name()));
+            }
+        }
+        if ui.button("reset").clicked() {
+            base.0 = K::default();
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            ui.separator();
+            presets(ui, &mut tuning, &mut base.0);
+        }
+    });
+    Ok(())
+}
+
+fn intensity_graph(ui: &mut egui::Ui, history: &VecDeque<f32>) {
+    let (rect, _) =
+        ui.allocate_exact_size(egui::vec2(ui.available_width(), 80.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(128));
+
+    let y = |value: f32| rect.bottom() - rect.height() * value.clamp(0.0, 1.0);
+    // where the hole starts to open, see KIntensity::hole
+    painter.hline(
+        rect.x_range(),
+        y(0.65),
+        egui::Stroke::new(1.0, egui::Color32::DARK_GRAY),
+    );
+    let points = history
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let x = rect.left() + rect.width() * i as f32 / (GRAPH_SAMPLES - 1) as f32;
+            egui::pos2(x, y(*value))
+        })
+        .collect();
+    painter.add(egui::Shape::line(
+        points,
+        egui::Stroke::new(1.5, egui::Color32::LIGHT_RED),
+    ));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn presets(ui: &mut egui::Ui, tuning: &mut KTuning, k: &mut K) {
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(&mut tuning.preset);
+        let path = std::path::Path::new(PRESETS).join(format!("{}.kfx.ron", tuning.preset));
+        if ui.button("save").clicked() {
+            tuning.status = match ron::ser::to_string_pretty(k, default())
+                .map_err(|e| e.to_string())
+                .and_then(|ron| std::fs::write(&path, ron).map_err(|e| e.to_string()))
+            {
+                Ok(()) => format!("saved {}", path.display()),
+                Err(e) => format!("could not save {}: {e}", path.display()),
+            };
+        }
+        if ui.button("load").clicked() {
+            tuning.status = match std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|ron| ron::de::from_str::<K>(&ron).map_err(|e| e.to_string()))
+            {
+                Ok(preset) => {
+                    *k = preset;
+                    format!("loaded {}", path.display())
+                }
+                Err(e) => format!("could not load {}: {e}", path.display()),
+            };
+        }
+    });
+    ui.label(&tuning.status);
+}
@@ -0,0 +1,331 @@
+use std::{path::Path, time::Duration};
+mod blob;
+mod controls;
+pub mod dialogue;
+mod document;
+pub mod footsteps;
+mod inspect;
+mod interact;
+pub mod k;
+mod k_audio;
+mod k_tuning;
+mod loading;
+mod player;
+pub mod post;
+pub mod seal;
+mod settings;
+mod sprint;
+mod throw;
+mod ui_input;
+mod utils;
+mod viewer;
+mod widgets;
+
+use avian3d::prelude::*;
+use bevy::{
+    dev_tools::picking_debug::{DebugPickingMode, DebugPickingPlugin},
+    ecs::{lifecycle::HookContext, world::DeferredWorld},
+    image::ImageSamplerDescriptor,
+    input::common_conditions::input_just_pressed,
+    prelude::*,
+    window::{CursorGrabMode, CursorOptions},
+};
+use bevy_easy_gif::GifPlugin;
+use bevy_egui::EguiPlugin;
+use bevy_enhanced_input::prelude::*;
+use bevy_inspector_egui::quick::WorldInspectorPlugin;
+use bevy_seedling::{
+    SeedlingPlugin,
+    sample::{AudioSample, SamplePlayer},
+};
+use bevy_skein::SkeinPlugin;
+
+use crate::{
+    blob::{Blob, FileBlob},
+    dialogue::{Dialogues, StartDialogue, intro},
+    interact::{Interactable, Interacted},
+    loading::Preload,
+    ui_input::{UiActivate, ui_pressed},
+    utils::ExampleUtilPlugin,
+    viewer::OpenDocument,
+    widgets::{FadeIn, credits_screen, l, timer},
+};
+
+const FILES: u32 = 1;
+/// Seconds the interact button is held to collect a file
+const FILE_HOLD: f32 = 0.6;
+
+/// The level scene, relative to the assets folder
+pub const LEVEL: &str = "room.glb";
+
+pub fn run() -> AppExit {
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins.set(ImagePlugin {
+        default_sampler: ImageSamplerDescriptor {
+            anisotropy_clamp: 1,
+            ..ImageSamplerDescriptor::linear()
+        },
+    }))
+    .insert_resource(UiPickingSettings {
+        require_markers: true,
+    })
+    .add_plugins((
+        GifPlugin,
+        SeedlingPlugin::default(),
+        EnhancedInputPlugin,
+        SkeinPlugin::default(),
+        PhysicsPlugins::default(),
+        PhysicsPickingPlugin,
+        PhysicsDebugPlugin,
+        DebugPickingPlugin,
+        EguiPlugin::default(),
+        WorldInspectorPlugin::new(),
+    ))
+    .insert_resource(DebugPickingMode::Normal)
+    .add_plugins(ExampleUtilPlugin)
+    .add_plugins((
+        player::plugin,
+        widgets::plugin,
+        blob::plugin,
+        loading::plugin,
+        viewer::plugin,
+        settings::plugin,
+        controls::plugin,
+        interact::plugin,
+        inspect::plugin,
+        throw::plugin,
+        ui_input::plugin,
+        post::plugin,
+        k::plugin,
+        k_audio::plugin,
+        k_tuning::plugin,
+    ))
+    .add_plugins((footsteps::plugin, sprint::plugin, dialogue::plugin))
+    .init_state::<GameState>()
+    .add_systems(Startup, setup)
+    .add_systems(OnEnter(GameState::Playing), start_run)
+    .add_systems(Update, tick_progress.run_if(in_state(GameState::Playing)))
+    .add_systems(
+        Update,
+        (
+            // clicking through dialogues and menus leaves the cursor alone
+            capture_cursor.run_if(input_just_pressed(MouseButton::Left).and(not(ui_pressed))),
+            release_cursor.run_if(input_just_pressed(KeyCode::Escape)),
+        ),
+    )
+    .insert_resource(Progress {
+        files_collected: 0,
+        timer: Timer::new(Duration::from_mins(5), TimerMode::Once),
+        documents: Vec::new(),
+    })
+    .init_resource::<Progress>()
+    .add_observer(collect_file)
+    .add_observer(on_file_collected)
+    .add_observer(on_w)
+    .add_observer(on_l);
+
+    app.run()
+}
+
+/// Used by the win and lose screens, preloaded with the level
+#[derive(Resource)]
+struct EndingAssets {
+    siren: Handle<AudioSample>,
+    font: Handle<Font>,
+}
+
+fn setup(mut cmd: Commands, assets: Res<AssetServer>, mut preload: ResMut<Preload>) {
+    let scene = assets.load(GltfAssetLabel::Scene(0).from_asset(LEVEL));
+    preload.add(scene.clone());
+    cmd.spawn((SceneRoot(scene),));
+
+    let ending = EndingAssets {
+        siren: assets.load("siren.ogg"),
+        font: assets.load("souls_font.ttf"),
+    };
+    preload.add(ending.siren.clone());
+    preload.add(ending.font.clone());
+    cmd.insert_resource(ending);
+}
+
+fn start_run(mut cmd: Commands) {
+    cmd.run_system_cached(intro);
+    cmd.spawn(timer());
+}
+
+fn capture_cursor(mut cursor: Single<&mut CursorOptions>) {
+    cursor.grab_mode = CursorGrabMode::Locked;
+    cursor.visible = false;
+}
+
+fn release_cursor(mut cursor: Single<&mut CursorOptions>) {
+    cursor.visible = true;
+    cursor.grab_mode = CursorGrabMode::None;
+}
+
+#[derive(States, Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub enum GameState {
+    /// Everything the level references is loaded behind a loading screen
+    #[default]
+    Loading,
+    Playing,
+}
+
+#[derive(Debug, PhysicsLayer, Default)]
+pub enum CollisionLayer {
+    #[default]
+    Default,
+    Player,
+    Prop,
+    /// Collected through [`interact::Interactable`] rather than picked up
+    Interactable,
+}
+
+#[derive(Component, Reflect)]
+#[require(Visibility)]
+#[require(Interactable = Interactable::new("Grab file", FILE_HOLD))]
+#[reflect(Component)]
+#[component(on_add = onadd_file)]
+#[type_path = "stoned"]
+pub struct File {
+    pub file: String,
+    pub sound: String,
+    pub dialogue: Dialogues,
+}
+
+/// Keeps files out of the physics pickup, even when they're also a [`DynamicProp`]
+fn onadd_file(mut w: DeferredWorld, ctx: HookContext) {
+    // queued so it lands after the layers required by the other components
+    w.commands().entity(ctx.entity).insert(CollisionLayers::new(
+        CollisionLayer::Interactable,
+        LayerMask::ALL,
+    ));
+}
+
+#[derive(Resource, Default)]
+pub struct Progress {
+    pub files_collected: u32,
+    pub timer: Timer,
+    /// Keeps the blobs of collected files loaded for the rest of the run
+    pub documents: Vec<Handle<Blob>>,
+}
+
+#[derive(Component, Reflect)]
+#[require(ColliderConstructor::ConvexHullFromMesh)]
+#[require(CollisionLayers::new(CollisionLayer::Prop, LayerMask::ALL))]
+#[require(RigidBody::Dynamic)]
+#[require(TransformInterpolation)]
+#[require(ColliderDensity)]
+#[reflect(Component, Default)]
+#[component(on_add = onadd_prop)]
+#[type_path = "stoned"]
+pub struct DynamicProp {
+    density: f32,
+}
+
+impl Default for DynamicProp {
+    fn default() -> Self {
+        Self { density: 1.0 }
+    }
+}
+
+fn onadd_prop(mut w: DeferredWorld, ctx: HookContext) {
+    debug!("prop added");
+    let propmesh = w.get::<DynamicProp>(ctx.entity).unwrap();
+    let propmesh_density = propmesh.density;
+    let mut density = w.get_mut::<ColliderDensity>(ctx.entity).unwrap();
+    density.0 = propmesh_density;
+}
+
+/// Shown next to a prop while it's inspected
+#[derive(Component, Reflect, Default, Clone)]
+#[reflect(Component, Default)]
+#[type_path = "stoned"]
+pub struct InspectInfo {
+    pub title: String,
+    pub description: String,
+}
+
+#[derive(Component, Reflect)]
+#[require(ColliderConstructor::TrimeshFromMesh)]
+#[require(CollisionLayers::new(CollisionLayer::Default, LayerMask::ALL))]
+#[require(ColliderDensity(3000.))]
+#[require(RigidBody::Static)]
+#[reflect(Component)]
+#[type_path = "stoned"]
+pub struct StaticProp;
+
+#[derive(EntityEvent)]
+pub struct FileCollected {
+    #[event_target]
+    pub file: Entity,
+}
+
+fn collect_file(on: On<Interacted>, mut cmd: Commands, files: Query<(), With<File>>) {
+    if files.contains(on.entity) {
+        cmd.trigger(FileCollected { file: on.entity });
+    }
+}
+
+fn on_file_collected(
+    on: On<FileCollected>,
+    mut cmd: Commands,
+    mut prog: ResMut<Progress>,
+    files: Query<(&File, &FileBlob)>,
+    ass: Res<AssetServer>,
+) {
+    debug!("file collected, yay");
+    let (file, blob) = files.get(on.file).unwrap();
+    cmd.spawn(SamplePlayer::new(ass.load(&file.sound)));
+    let name = Path::new(&file.file)
+        .file_stem()
+        .map_or_else(|| file.file.clone(), |n| n.to_string_lossy().into_owned());
+    cmd.trigger(OpenDocument {
+        blob: blob.0.clone(),
+        name,
+    });
+    prog.documents.push(blob.0.clone());
+    prog.files_collected += 1;
+    cmd.queue(StartDialogue(file.dialogue));
+    cmd.entity(on.file).despawn();
+    if prog.files_collected == FILES {
+        cmd.trigger(W);
+    }
+}
+
+fn tick_progress(mut prog: ResMut<Progress>, time: Res<Time>, mut cmd: Commands) {
+    if prog.timer.tick(time.delta()).just_finished() {
+        cmd.trigger(BigL);
+    }
+}
+
+#[derive(Event)]
+pub struct W;
+
+#[derive(Event)]
+pub struct BigL;
+
+fn on_w(_: On<W>, mut cmd: Commands, ending: Res<EndingAssets>) {
+    debug!("W");
+    cmd.spawn(SamplePlayer::new(ending.siren.clone()).looping());
+    cmd.spawn((
+        l(ending.font.clone(), "Du Wurdest Verepp-elt"),
+        FadeIn::new(1.5),
+    ))
+    .observe(trigger_credits);
+}
+
+fn on_l(_: On<BigL>, mut cmd: Commands, ending: Res<EndingAssets>) {
+    debug!("L");
+    cmd.spawn((
+        l(ending.font.clone(), "Du Wurdest Gestein-Rolled"),
+        FadeIn::new(1.5),
+    ))
+    .observe(trigger_credits);
+}
+
+fn trigger_credits(on: On<UiActivate>, mut cmd: Commands, prog: Res<Progress>) {
+    let time = prog.timer.elapsed().as_secs_f32();
+    cmd.entity(on.entity).despawn();
+    cmd.spawn(credits_screen(time));
+}
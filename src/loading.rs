@@ -0,0 +1,137 @@
+use bevy::{
+    asset::{LoadState, RecursiveDependencyLoadState, UntypedAssetId},
+    prelude::*,
+    scene::SceneInstanceReady,
+};
+use bevy_seedling::sample::AudioSample;
+
+use crate::{
+    File, GameState,
+    blob::Blob,
+    ui_input::UiActivate,
+    widgets::{LoadingBar, LoadingStatus, ProgressFill, loading_screen},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<Preload>()
+        .add_systems(OnEnter(GameState::Loading), start_loading)
+        .add_systems(Update, track_loading.run_if(in_state(GameState::Loading)))
+        .add_observer(on_scene_ready)
+        .add_observer(preload_file);
+}
+
+/// Every asset the level references, plugins add their own at startup.
+/// The handles are kept for the whole run so nothing gets unloaded and loaded again.
+#[derive(Resource, Default)]
+pub struct Preload {
+    handles: Vec<UntypedHandle>,
+    scene_ready: bool,
+    /// every handle finished loading, one way or the other
+    settled: bool,
+    failures: Vec<String>,
+}
+
+impl Preload {
+    pub fn add(&mut self, handle: impl Into<UntypedHandle>) {
+        self.handles.push(handle.into());
+    }
+}
+
+fn start_loading(mut cmd: Commands) {
+    cmd.spawn((loading_screen(), DespawnOnExit(GameState::Loading)))
+        .observe(continue_anyway);
+}
+
+/// Files only become known once the level is spawned, so their assets join the list late
+fn preload_file(
+    on: On<Add, File>,
+    files: Query<&File>,
+    mut preload: ResMut<Preload>,
+    ass: Res<AssetServer>,
+) {
+    let file = files.get(on.entity).unwrap();
+    preload.add(ass.load::<AudioSample>(&file.sound));
+    preload.add(ass.load::<Blob>(&file.file));
+}
+
+fn on_scene_ready(_: On<SceneInstanceReady>, mut preload: ResMut<Preload>) {
+    preload.scene_ready = true;
+}
+
+enum Status {
+    Loading,
+    Loaded,
+    Failed(String),
+}
+
+fn status(ass: &AssetServer, id: UntypedAssetId) -> Status {
+    match ass.get_load_states(id) {
+        Some((LoadState::Failed(e), ..) | (.., RecursiveDependencyLoadState::Failed(e))) => {
+            let path = ass
+                .get_path(id)
+                .map_or_else(|| format!("{id:?}"), |p| p.to_string());
+            Status::Failed(format!("{path}: {e}"))
+        }
+        Some((LoadState::Loaded, _, RecursiveDependencyLoadState::Loaded)) => Status::Loaded,
+        _ => Status::Loading,
+    }
+}
+
+fn track_loading(
+    mut preload: ResMut<Preload>,
+    ass: Res<AssetServer>,
+    mut bar: Single<&mut ProgressFill, With<LoadingBar>>,
+    mut label: Single<&mut Text, With<LoadingStatus>>,
+    mut next: ResMut<NextState<GameState>>,
+) {
+    let mut done = 0;
+    let mut failures = Vec::new();
+    for handle in &preload.handles {
+        match status(&ass, handle.id()) {
+            Status::Loading => {}
+            Status::Loaded => done += 1,
+            Status::Failed(e) => {
+                done += 1;
+                failures.push(e);
+            }
+        }
+    }
+
+    for failure in &failures {
+        if !preload.failures.contains(failure) {
+            error!("failed to load {failure}");
+        }
+    }
+    preload.failures = failures;
+
+    let total = preload.handles.len().max(1);
+    bar.0 = done as f32 / total as f32;
+
+    preload.settled = preload.scene_ready && done == preload.handles.len();
+    if !preload.failures.is_empty() {
+        label.0 = format!(
+            "{} asset(s) failed to load:\n{}\n\n{}",
+            preload.failures.len(),
+            preload.failures.join("\n"),
+            if preload.settled {
+                "click to continue anyway"
+            } else {
+                "still loading..."
+            }
+        );
+    } else if preload.settled {
+        next.set(GameState::Playing);
+    } else {
+        label.0 = format!("{done}/{}", preload.handles.len());
+    }
+}
+
+fn continue_anyway(
+    _: On<UiActivate>,
+    preload: Res<Preload>,
+    mut next: ResMut<NextState<GameState>>,
+) {
+    if preload.settled {
+        next.set(GameState::Playing);
+    }
+}
@@ -1,8 +1,4 @@
-use avian_pickup::{
-    Holding,
-    actor::AvianPickupActorState,
-    input::{AvianPickupAction, AvianPickupInput},
-};
+use avian_pickup::input::{AvianPickupAction, AvianPickupInput};
 use avian3d::prelude::*;
 use bevy::{
     anti_alias::fxaa::Fxaa,
@@ -13,18 +9,21 @@ use bevy::{
     render::view::Hdr,
 };
 use bevy_ahoy::{PickupHoldConfig, PickupPullConfig, prelude::*};
-use bevy_enhanced_input::prelude::Press;
 use bevy_enhanced_input::prelude::*;
 
-use crate::{CollisionLayer, File, FileCollected, Progress, k::K};
+use crate::{CollisionLayer, Progress, controls::player_actions, k::KBase, settings::Settings};
 
 pub(super) fn plugin(app: &mut App) {
     app.add_plugins(AhoyPlugins::default())
-        .add_systems(Update, check_page_collect)
+        .add_systems(
+            Update,
+            (
+                switch_player_input,
+                apply_fov.run_if(resource_changed::<Settings>),
+            ),
+        )
         .add_input_context::<PlayerInput>()
         .init_resource::<PlayerRes>()
-        .add_observer(on_enable)
-        .add_observer(on_disable)
         .add_observer(spawn_player);
 }
 
@@ -41,17 +40,16 @@ pub struct PlayerRes {
     pub cam: Option<Entity>,
 }
 
-#[derive(Event)]
-pub struct DisablePlayer;
-
-#[derive(Event)]
-pub struct EnablePlayer;
+/// UI that takes the input away from the player while it exists
+#[derive(Component, Default)]
+pub struct BlocksPlayer;
 
 fn spawn_player(
     t: On<Add, Player>,
     trans: Query<&Transform>,
     mut cmd: Commands,
     mut playerres: ResMut<PlayerRes>,
+    settings: Res<Settings>,
 ) {
     debug!("spawning player");
     let player = cmd
@@ -66,46 +64,12 @@ fn spawn_player(
             // The KCC currently behaves best when using a cylinder
             Collider::cylinder(0.4, 1.8),
             //StableGround::default(),
-            // Configure inputs. The actions `Movement`, `Jump`, etc. are provided by Ahoy, you just need to bind them.
+            // Configure inputs. The actions `Movement`, `Jump`, etc. are provided by Ahoy,
+            // bound from the settings so they can be rebound.
             PlayerInput,
-            actions!(PlayerInput[
-                (
-                    Action::<Movement>::new(),
-                    // Normalize the input vector
-                    DeadZone::default(),
-                    Bindings::spawn((
-                        Cardinal::wasd_keys(),
-                        Axial::left_stick()
-                    ))
-                ),
-                (
-                    Action::<Jump>::new(),
-                    bindings![KeyCode::Space,  GamepadButton::South],
-                ),
-                (
-                    Action::<Crouch>::new(),
-                    bindings![KeyCode::ControlLeft, GamepadButton::LeftTrigger2],
-                ),
-                (
-                    Action::<RotateCamera>::new(),
-                    Bindings::spawn((
-                        Spawn((Binding::mouse_motion(), Scale::splat(0.07))),
-                        Axial::right_stick().with((Scale::splat(4.0), DeadZone::default())),
-                    ))
-                ),
-                (
-                    Action::<PullObject>::new(),
-                    ActionSettings { consume_input: true, ..default() },
-                    Press::default(),
-                    bindings![KeyCode::KeyE],
-                ),
-                (
-                    Action::<ThrowObject>::new(),
-                    ActionSettings { consume_input: true, ..default() },
-                    Press::default(),
-                    bindings![KeyCode::KeyQ],
-                ),
-            ]),
+            // enabled once the intro dialogue is dismissed, see `switch_player_input`
+            ContextActivity::<PlayerInput>::INACTIVE,
+            player_actions(&settings.controls, &settings.player),
         ))
         .id();
 
@@ -115,7 +79,7 @@ fn spawn_player(
     let playercam = cmd
         .spawn((
             Name::new("player cam"),
-            K::default(),
+            KBase::default(),
             PickupConfig {
                 prop_filter: SpatialQueryFilter::from_mask(CollisionLayer::Prop),
                 actor_filter: SpatialQueryFilter::from_mask(CollisionLayer::Player),
@@ -150,6 +114,10 @@ fn spawn_player(
                 Tonemapping::BlenderFilmic,
             ),
             Camera3d::default(),
+            Projection::from(PerspectiveProjection {
+                fov: settings.player.fov.to_radians(),
+                ..default()
+            }),
             // Enable the optional builtin camera controller
             CharacterControllerCameraOf::new(player),
         ))
@@ -158,33 +126,29 @@ fn spawn_player(
     playerres.cam = Some(playercam);
 }
 
-fn check_page_collect(
-    mut cmd: Commands,
-    mut actor_state: Single<(Entity, &mut AvianPickupActorState)>,
-    files: Query<Entity, With<File>>,
+fn apply_fov(
+    settings: Res<Settings>,
+    mut projections: Query<&mut Projection, With<CharacterControllerCameraOf>>,
 ) {
-    let AvianPickupActorState::Holding(e) = actor_state.1.as_ref() else {
-        return;
-    };
-
-    if !files.contains(*e) {
-        return;
-    }
-
-    cmd.trigger(FileCollected { file: *e });
-    *actor_state.1.as_mut() = AvianPickupActorState::Idle;
-    cmd.entity(actor_state.0).remove::<Holding>();
-}
-
-fn on_enable(_: On<EnablePlayer>, mut cmd: Commands, player: Res<PlayerRes>) {
-    if let Some(e) = player.player {
-        cmd.entity(e).insert(ContextActivity::<PlayerInput>::ACTIVE);
+    for mut projection in &mut projections {
+        if let Projection::Perspective(perspective) = projection.as_mut() {
+            perspective.fov = settings.player.fov.to_radians();
+        }
     }
 }
 
-fn on_disable(_: On<DisablePlayer>, mut cmd: Commands, player: Res<PlayerRes>) {
-    if let Some(e) = player.player {
-        cmd.entity(e)
-            .insert(ContextActivity::<PlayerInput>::INACTIVE);
+fn switch_player_input(
+    mut cmd: Commands,
+    blockers: Query<(), With<BlocksPlayer>>,
+    player: Single<(Entity, &ContextActivity<PlayerInput>)>,
+) {
+    let active = blockers.is_empty();
+    let (entity, activity) = player.into_inner();
+    if **activity != active {
+        cmd.entity(entity).insert(if active {
+            ContextActivity::<PlayerInput>::ACTIVE
+        } else {
+            ContextActivity::<PlayerInput>::INACTIVE
+        });
     }
 }
@@ -0,0 +1,476 @@
+//! Fullscreen passes that run after tonemapping, [`K`] and the smaller ones stacked on it.
+//!
+//! Every pass is a [`FullscreenMaterial`] component with its own WGSL file and a
+//! [`PostPass::ORDER`] that places it in the chain. A camera only pays for the passes it
+//! has a component for, so the smaller ones are only added while they show something.
+
+use std::sync::OnceLock;
+
+use avian_pickup::actor::AvianPickupActorState;
+use bevy::{
+    core_pipeline::{
+        core_3d::graph::Node3d,
+        fullscreen_material::{FullscreenMaterial, FullscreenMaterialPlugin},
+    },
+    ecs::component::Mutable,
+    prelude::*,
+    render::{
+        extract_component::ExtractComponent,
+        render_graph::{InternedRenderLabel, RenderLabel},
+        render_resource::ShaderType,
+    },
+    shader::{Shader, ShaderRef},
+};
+
+use crate::{k::K, loading::Preload, viewer::DocumentViewer};
+
+/// Seconds for the document focus to fade in and out
+const FOCUS_FADE: f32 = 0.3;
+/// Damage fades out at this much per second
+const DAMAGE_DECAY: f32 = 1.5;
+/// Seconds for the tape look to come and go with the camcorder
+const VHS_FADE: f32 = 0.5;
+
+pub(super) fn plugin(app: &mut App) {
+    add_passes(app);
+    app.add_systems(Update, (focus_documents, fade_damage, run_vhs))
+        .add_observer(on_hurt);
+}
+
+/// A [`FullscreenMaterial`] in the post process chain
+pub trait PostPass: FullscreenMaterial {
+    /// Where the pass runs, lower runs earlier
+    const ORDER: u32;
+}
+
+struct Pass {
+    order: u32,
+    label: InternedRenderLabel,
+    add: fn(&mut App),
+}
+
+impl Pass {
+    fn new<M: PostPass>() -> Self {
+        Self {
+            order: M::ORDER,
+            label: M::node_label().intern(),
+            add: |app| {
+                app.add_plugins(FullscreenMaterialPlugin::<M>::default())
+                    .add_systems(
+                        Startup,
+                        preload_shader::<M>.run_if(resource_exists::<Preload>),
+                    );
+            },
+        }
+    }
+}
+
+/// Labels of the passes, first to last, see [`add_passes`]
+static CHAIN: OnceLock<Vec<InternedRenderLabel>> = OnceLock::new();
+
+/// Adds every pass sorted by [`PostPass::ORDER`].
+/// A pass hooks up to the one before it when it's added, so they have to go in in order.
+pub fn add_passes(app: &mut App) {
+    let mut passes = [
+        Pass::new::<K>(),
+        Pass::new::<DocumentFocus>(),
+        Pass::new::<DamageVignette>(),
+        Pass::new::<Vhs>(),
+    ];
+    passes.sort_by_key(|pass| pass.order);
+    CHAIN.get_or_init(|| passes.iter().map(|pass| pass.label).collect());
+    for pass in &passes {
+        (pass.add)(app);
+    }
+}
+
+/// Compiling a pass on first use stalls a frame, so its shader joins the loading screen
+fn preload_shader<M: FullscreenMaterial>(ass: Res<AssetServer>, mut preload: ResMut<Preload>) {
+    if let ShaderRef::Path(path) = M::fragment_shader() {
+        preload.add(ass.load::<Shader>(path));
+    }
+}
+
+/// Render graph edges for a pass, for [`FullscreenMaterial::node_edges`].
+///
+/// Only the edge into the pass is added, the next pass adds the edge out,
+/// so no edge is added twice.
+pub fn chain_edges<M: FullscreenMaterial>() -> Vec<InternedRenderLabel> {
+    let chain = CHAIN
+        .get()
+        .expect("fullscreen materials have to be added with post::add_passes");
+    let label = M::node_label().intern();
+    let index = chain
+        .iter()
+        .position(|l| *l == label)
+        .expect("fullscreen materials have to be listed in post::add_passes");
+
+    let mut edges = vec![
+        index
+            .checked_sub(1)
+            .map_or(Node3d::Tonemapping.intern(), |i| chain[i]),
+        label,
+    ];
+    if index == chain.len() - 1 {
+        edges.push(Node3d::EndMainPassPostProcessing.intern());
+    }
+    edges
+}
+
+/// Moves a pass's `amount` towards `target` by at most `step`.
+/// The pass is added to the cameras once there's something to show
+/// and taken off again once it faded out.
+fn fade_pass<M: Component<Mutability = Mutable> + Default>(
+    cmd: &mut Commands,
+    cameras: &mut Query<(Entity, Option<&mut M>), With<Camera3d>>,
+    amount: fn(&mut M) -> &mut f32,
+    target: f32,
+    step: f32,
+) {
+    for (camera, pass) in cameras {
+        match pass {
+            Some(mut pass) => {
+                let value = amount(&mut pass);
+                *value += (target - *value).clamp(-step, step);
+                if *value <= 0.0 && target <= 0.0 {
+                    cmd.entity(camera).remove::<M>();
+                }
+            }
+            None if target > 0.0 => {
+                let mut pass = M::default();
+                *amount(&mut pass) = step.min(target);
+                cmd.entity(camera).insert(pass);
+            }
+            None => {}
+        }
+    }
+}
+
+const _: () = assert!(
+    size_of::<DocumentFocus>().is_multiple_of(16)
+        && size_of::<DamageVignette>().is_multiple_of(16)
+        && size_of::<Vhs>().is_multiple_of(16),
+    "uniforms have to be padded to 16 bytes for webgl2"
+);
+
+/// Desaturates and darkens the world while a document is open
+#[derive(Component, ExtractComponent, Reflect, Clone, Copy, ShaderType)]
+#[reflect(Component, Default)]
+pub struct DocumentFocus {
+    /// 0.0 - 1.0, faded in and out by the viewer
+    pub amount: f32,
+    /// saturation left at full focus
+    pub saturation: f32,
+    /// brightness left at full focus
+    pub brightness: f32,
+    /// uniforms are 16 byte aligned on webgl2
+    _webgl2_padding: f32,
+}
+
+impl Default for DocumentFocus {
+    fn default() -> Self {
+        Self {
+            amount: 0.0,
+            saturation: 0.15,
+            brightness: 0.5,
+            _webgl2_padding: 0.0,
+        }
+    }
+}
+
+impl PostPass for DocumentFocus {
+    const ORDER: u32 = 1;
+}
+
+impl FullscreenMaterial for DocumentFocus {
+    fn fragment_shader() -> ShaderRef {
+        "document_focus.wgsl".into()
+    }
+
+    fn node_edges() -> Vec<InternedRenderLabel> {
+        chain_edges::<Self>()
+    }
+}
+
+/// Red pulsing edges, added by [`Hurt`] and fading by itself
+#[derive(Component, ExtractComponent, Reflect, Clone, Copy, ShaderType)]
+#[reflect(Component, Default)]
+pub struct DamageVignette {
+    /// 0.0 - 1.0
+    pub amount: f32,
+    pub time: f32,
+    /// how far the red reaches in from the edges 0.0 - 1.0
+    pub reach: f32,
+    /// uniforms are 16 byte aligned on webgl2
+    _webgl2_padding: f32,
+}
+
+impl Default for DamageVignette {
+    fn default() -> Self {
+        Self {
+            amount: 0.0,
+            time: 0.0,
+            reach: 0.45,
+            _webgl2_padding: 0.0,
+        }
+    }
+}
+
+impl DamageVignette {
+    pub fn hit(&mut self, amount: f32) {
+        self.amount = (self.amount + amount).min(1.0);
+    }
+}
+
+/// The player got hurt, 0.0 - 1.0
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Hurt(pub f32);
+
+impl PostPass for DamageVignette {
+    const ORDER: u32 = 2;
+}
+
+impl FullscreenMaterial for DamageVignette {
+    fn fragment_shader() -> ShaderRef {
+        "damage_vignette.wgsl".into()
+    }
+
+    fn node_edges() -> Vec<InternedRenderLabel> {
+        chain_edges::<Self>()
+    }
+}
+
+/// Tape look to match the 1990s camera prop: scanlines, colour bleed, tracking noise.
+/// It fades in while a [`Camcorder`] is held
+#[derive(Component, ExtractComponent, Reflect, Clone, Copy, ShaderType)]
+#[reflect(Component, Default)]
+pub struct Vhs {
+    /// 0.0 turns it off
+    pub intensity: f32,
+    pub time: f32,
+    /// scanlines over the screen height
+    pub scanlines: f32,
+    /// 0.0 - 1.0
+    pub noise: f32,
+}
+
+impl Default for Vhs {
+    fn default() -> Self {
+        Self {
+            intensity: 0.0,
+            time: 0.0,
+            scanlines: 240.0,
+            noise: 0.3,
+        }
+    }
+}
+
+impl PostPass for Vhs {
+    // last so the tape look covers everything
+    const ORDER: u32 = 3;
+}
+
+/// A prop that shows the world through [`Vhs`] while it's held
+#[derive(Component, Reflect, Default, Clone, Copy)]
+#[reflect(Component, Default)]
+#[type_path = "stoned"]
+pub struct Camcorder;
+
+impl FullscreenMaterial for Vhs {
+    fn fragment_shader() -> ShaderRef {
+        "vhs.wgsl".into()
+    }
+
+    fn node_edges() -> Vec<InternedRenderLabel> {
+        chain_edges::<Self>()
+    }
+}
+
+fn focus_documents(
+    mut cmd: Commands,
+    time: Res<Time>,
+    viewers: Query<(), With<DocumentViewer>>,
+    mut cameras: Query<(Entity, Option<&mut DocumentFocus>), With<Camera3d>>,
+) {
+    let target = if viewers.is_empty() { 0.0 } else { 1.0 };
+    let step = time.delta_secs() / FOCUS_FADE;
+    fade_pass(&mut cmd, &mut cameras, |f| &mut f.amount, target, step);
+}
+
+fn on_hurt(
+    on: On<Hurt>,
+    mut cmd: Commands,
+    mut cameras: Query<(Entity, Option<&mut DamageVignette>), With<Camera3d>>,
+) {
+    for (camera, damage) in &mut cameras {
+        match damage {
+            Some(mut damage) => damage.hit(on.0),
+            None => {
+                let mut damage = DamageVignette::default();
+                damage.hit(on.0);
+                cmd.entity(camera).insert(damage);
+            }
+        }
+    }
+}
+
+fn fade_damage(
+    mut cmd: Commands,
+    time: Res<Time>,
+    mut cameras: Query<(Entity, Option<&mut DamageVignette>), With<Camera3d>>,
+) {
+    for (_, damage) in &mut cameras {
+        if let Some(mut damage) = damage {
+            damage.time = time.elapsed_secs();
+        }
+    }
+    let step = DAMAGE_DECAY * time.delta_secs();
+    fade_pass(&mut cmd, &mut cameras, |d| &mut d.amount, 0.0, step);
+}
+
+fn run_vhs(
+    mut cmd: Commands,
+    time: Res<Time>,
+    actors: Query<&AvianPickupActorState>,
+    camcorders: Query<(), With<Camcorder>>,
+    mut cameras: Query<(Entity, Option<&mut Vhs>), With<Camera3d>>,
+) {
+    let holding = actors.iter().any(|state| match state {
+        AvianPickupActorState::Holding(prop) => camcorders.contains(*prop),
+        _ => false,
+    });
+    for (_, vhs) in &mut cameras {
+        if let Some(mut vhs) = vhs {
+            vhs.time = time.elapsed_secs();
+        }
+    }
+    let target = if holding { 1.0 } else { 0.0 };
+    let step = time.delta_secs() / VHS_FADE;
+    fade_pass(&mut cmd, &mut cameras, |v| &mut v.intensity, target, step);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        reflect::Struct,
+        render::render_resource::encase::{UniformBuffer, internal::WriteInto},
+    };
+
+    use super::*;
+
+    /// Stands in for the bevy `#import`, naga doesn't know about those
+    const FULLSCREEN_VERTEX_OUTPUT: &str = "struct FullscreenVertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+";
+
+    /// `T` with every field set to a value no other field has,
+    /// with the kind and bytes of each field
+    fn marked<T: Struct + Default>() -> (T, Vec<(naga::ScalarKind, [u8; 4])>) {
+        let mut value = T::default();
+        let mut fields = Vec::new();
+        for i in 0..value.field_len() {
+            let name = value.name_at(i).unwrap_or("?").to_owned();
+            let field = value.field_at_mut(i).unwrap();
+            let marker = i as u32 + 1;
+            if let Some(float) = field.try_downcast_mut::<f32>() {
+                *float = marker as f32;
+                fields.push((naga::ScalarKind::Float, float.to_le_bytes()));
+            } else if let Some(uint) = field.try_downcast_mut::<u32>() {
+                *uint = marker;
+                fields.push((naga::ScalarKind::Uint, uint.to_le_bytes()));
+            } else {
+                panic!("{name}: only f32 and u32 uniforms are checked so far");
+            }
+        }
+        (value, fields)
+    }
+
+    /// The WGSL struct `name` has to lay out the fields of `T` like encase does:
+    /// same order, types and offsets, and a size webgl2 accepts.
+    /// A mismatch only shows up as garbled uniforms on webgl2 otherwise.
+    fn check_uniform<T: Struct + Default + ShaderType + WriteInto>(shader: &str, name: &str) {
+        let path = format!("{}/assets/{shader}", env!("CARGO_MANIFEST_DIR"));
+        let source = std::fs::read_to_string(&path).unwrap();
+        let source: String = std::iter::once(FULLSCREEN_VERTEX_OUTPUT)
+            .chain(source.lines().filter(|l| !l.trim_start().starts_with('#')))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let module = naga::front::wgsl::parse_str(&source)
+            .unwrap_or_else(|e| panic!("{shader}: {}", e.emit_to_string(&source)));
+
+        let (members, span) = module
+            .types
+            .iter()
+            .find_map(|(_, ty)| match &ty.inner {
+                naga::TypeInner::Struct { members, span } if ty.name.as_deref() == Some(name) => {
+                    Some((members, *span))
+                }
+                _ => None,
+            })
+            .unwrap_or_else(|| panic!("{shader}: no struct {name}"));
+
+        let (value, fields) = marked::<T>();
+        let mut buffer = UniformBuffer::new(Vec::<u8>::new());
+        buffer.write(&value).unwrap();
+        let bytes = buffer.into_inner();
+
+        assert_eq!(
+            members.len(),
+            fields.len(),
+            "{shader}: field count of struct {name}"
+        );
+        for (i, (member, (kind, marker))) in members.iter().zip(fields).enumerate() {
+            let field = member.name.as_deref().unwrap_or("?");
+            assert_eq!(
+                Some(field),
+                value.name_at(i),
+                "{shader}: field {i} of struct {name}"
+            );
+            let naga::TypeInner::Scalar(scalar) = module.types[member.ty].inner else {
+                panic!("{shader}: {name}.{field} isn't a scalar");
+            };
+            assert_eq!(
+                (scalar.kind, scalar.width),
+                (kind, 4),
+                "{shader}: type of {name}.{field}"
+            );
+            let offset = member.offset as usize;
+            assert_eq!(
+                bytes.get(offset..offset + 4),
+                Some(&marker[..]),
+                "{shader}: offset of {name}.{field}"
+            );
+        }
+        assert_eq!(
+            u64::from(span),
+            T::min_size().get(),
+            "{shader}: size of struct {name}"
+        );
+        assert!(
+            span.is_multiple_of(16),
+            "{shader}: struct {name} is {span} bytes, webgl2 needs a multiple of 16"
+        );
+    }
+
+    #[test]
+    fn k_uniform() {
+        check_uniform::<K>("k.wgsl", "K");
+    }
+
+    #[test]
+    fn document_focus_uniform() {
+        check_uniform::<DocumentFocus>("document_focus.wgsl", "DocumentFocus");
+    }
+
+    #[test]
+    fn damage_vignette_uniform() {
+        check_uniform::<DamageVignette>("damage_vignette.wgsl", "DamageVignette");
+    }
+
+    #[test]
+    fn vhs_uniform() {
+        check_uniform::<Vhs>("vhs.wgsl", "Vhs");
+    }
+}
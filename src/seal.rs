@@ -0,0 +1,246 @@
+//! Sealed blobs: ChaCha20-Poly1305 encrypted payloads that unlock through progress.
+//!
+//! The key is never shipped, it's derived from the documents the player has to collect
+//! first. That keeps the bonus files unreadable for anyone digging through the bundle
+//! without also digging up every other file, which is all we're after.
+//!
+//! Layout: `MAGIC | requires | nonce (12 bytes) | ciphertext + tag`
+//!
+//! `requires` names the documents the key is derived from, a `u16` count followed by
+//! `u16` length prefixed names. It's authenticated along with the payload.
+
+use chacha20poly1305::{
+    ChaCha20Poly1305, Key, KeyInit, Nonce,
+    aead::{Aead, Payload},
+};
+use thiserror::Error;
+
+pub const MAGIC: &[u8; 8] = b"STONEDS1";
+const NONCE_LEN: usize = 12;
+const KDF_CONTEXT: &str = "stoned 2026-02 sealed blob key";
+
+/// Key for sealing and unsealing, see [`SealKey::derive`]
+#[derive(Clone)]
+pub struct SealKey([u8; 32]);
+
+impl SealKey {
+    /// Derives the key from named documents, the order they're passed in doesn't matter
+    pub fn derive<'a>(documents: impl IntoIterator<Item = (&'a str, &'a [u8])>) -> Self {
+        let mut documents: Vec<_> = documents.into_iter().collect();
+        documents.sort_by_key(|(name, _)| *name);
+
+        let mut hasher = blake3::Hasher::new_derive_key(KDF_CONTEXT);
+        for (name, bytes) in documents {
+            // length prefixes so ("ab", "c") and ("a", "bc") don't collide
+            hasher.update(&(name.len() as u64).to_le_bytes());
+            hasher.update(name.as_bytes());
+            hasher.update(&(bytes.len() as u64).to_le_bytes());
+            hasher.update(bytes);
+        }
+        Self(*hasher.finalize().as_bytes())
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(Key::from_slice(&self.0))
+    }
+}
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum SealError {
+    #[error("payload is not sealed")]
+    NotSealed,
+    #[error("sealed payload is truncated")]
+    Truncated,
+    #[error("sealed payload has a malformed list of required documents")]
+    Malformed,
+    /// More than `u16::MAX` documents, or a name longer than `u16::MAX` bytes
+    #[error("list of required documents doesn't fit the header")]
+    TooLong,
+    /// Wrong key or the payload was tampered with, poly1305 can't tell which
+    #[error("sealed payload failed authentication")]
+    Authentication,
+}
+
+pub fn is_sealed(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Seals `plaintext` with a key derived from the `requires` documents, see [`SealKey::derive`]
+pub fn seal<'a>(
+    plaintext: &[u8],
+    requires: impl IntoIterator<Item = &'a str>,
+    key: &SealKey,
+) -> Result<Vec<u8>, SealError> {
+    let mut header = Vec::new();
+    let requires: Vec<_> = requires.into_iter().collect();
+    put_u16(&mut header, requires.len())?;
+    for name in requires {
+        put_u16(&mut header, name.len())?;
+        header.extend_from_slice(name.as_bytes());
+    }
+
+    // every key seals a single, fixed set of payloads,
+    // so a nonce derived from the payload itself never repeats for different contents
+    let nonce = blake3::keyed_hash(&key.0, plaintext);
+    let nonce = Nonce::from_slice(&nonce.as_bytes()[..NONCE_LEN]);
+    let payload = Payload {
+        msg: plaintext,
+        aad: &header,
+    };
+    let ciphertext = key
+        .cipher()
+        .encrypt(nonce, payload)
+        .expect("chacha20poly1305 encryption is infallible for in-memory payloads");
+
+    let mut sealed = Vec::with_capacity(MAGIC.len() + header.len() + NONCE_LEN + ciphertext.len());
+    sealed.extend_from_slice(MAGIC);
+    sealed.extend_from_slice(&header);
+    sealed.extend_from_slice(nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn put_u16(bytes: &mut Vec<u8>, n: usize) -> Result<(), SealError> {
+    let n = u16::try_from(n).map_err(|_| SealError::TooLong)?;
+    bytes.extend_from_slice(&n.to_le_bytes());
+    Ok(())
+}
+
+/// Names of the documents the key of a sealed payload is derived from
+pub fn requires(sealed: &[u8]) -> Result<Vec<&str>, SealError> {
+    Ok(split(sealed)?.requires)
+}
+
+pub fn unseal(sealed: &[u8], key: &SealKey) -> Result<Vec<u8>, SealError> {
+    let parts = split(sealed)?;
+    if parts.payload.len() < NONCE_LEN {
+        return Err(SealError::Truncated);
+    }
+    let (nonce, ciphertext) = parts.payload.split_at(NONCE_LEN);
+    let payload = Payload {
+        msg: ciphertext,
+        aad: parts.header,
+    };
+    key.cipher()
+        .decrypt(Nonce::from_slice(nonce), payload)
+        .map_err(|_| SealError::Authentication)
+}
+
+struct Parts<'a> {
+    requires: Vec<&'a str>,
+    /// `requires` as it's stored, authenticated with the payload
+    header: &'a [u8],
+    /// nonce and ciphertext
+    payload: &'a [u8],
+}
+
+fn split(sealed: &[u8]) -> Result<Parts<'_>, SealError> {
+    let payload = sealed.strip_prefix(MAGIC).ok_or(SealError::NotSealed)?;
+    let mut rest = payload;
+    let count = take_u16(&mut rest)?;
+    let mut names = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len = take_u16(&mut rest)?;
+        let name = take(&mut rest, len)?;
+        names.push(std::str::from_utf8(name).map_err(|_| SealError::Malformed)?);
+    }
+    Ok(Parts {
+        requires: names,
+        header: &payload[..payload.len() - rest.len()],
+        payload: rest,
+    })
+}
+
+fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Result<&'a [u8], SealError> {
+    let (head, tail) = bytes.split_at_checked(n).ok_or(SealError::Truncated)?;
+    *bytes = tail;
+    Ok(head)
+}
+
+fn take_u16(bytes: &mut &[u8]) -> Result<usize, SealError> {
+    let b = take(bytes, 2)?;
+    Ok(u16::from_le_bytes([b[0], b[1]]) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> SealKey {
+        SealKey::derive([("a.pdf", b"first".as_slice()), ("b.pdf", b"second")])
+    }
+
+    #[test]
+    fn roundtrip() {
+        let sealed = seal(b"bonus", ["a.pdf", "b.pdf"], &key()).unwrap();
+        assert!(is_sealed(&sealed));
+        assert_eq!(requires(&sealed).unwrap(), ["a.pdf", "b.pdf"]);
+        assert_eq!(unseal(&sealed, &key()).unwrap(), b"bonus");
+    }
+
+    #[test]
+    fn key_ignores_document_order() {
+        let sealed = seal(b"bonus", ["a.pdf", "b.pdf"], &key()).unwrap();
+        let key = SealKey::derive([("b.pdf", b"second".as_slice()), ("a.pdf", b"first")]);
+        assert_eq!(unseal(&sealed, &key).unwrap(), b"bonus");
+    }
+
+    #[test]
+    fn wrong_key_fails() {
+        let sealed = seal(b"bonus", ["a.pdf", "b.pdf"], &key()).unwrap();
+        let key = SealKey::derive([("a.pdf", b"first".as_slice())]);
+        assert!(matches!(
+            unseal(&sealed, &key),
+            Err(SealError::Authentication)
+        ));
+    }
+
+    #[test]
+    fn tampered_requirements_fail() {
+        let mut sealed = seal(b"bonus", ["a.pdf", "b.pdf"], &key()).unwrap();
+        // a.pdf -> c.pdf
+        let at = MAGIC.len() + 4;
+        sealed[at] = b'c';
+        assert_eq!(requires(&sealed).unwrap(), ["c.pdf", "b.pdf"]);
+        assert!(matches!(
+            unseal(&sealed, &key()),
+            Err(SealError::Authentication)
+        ));
+    }
+
+    #[test]
+    fn malformed_input() {
+        assert!(matches!(
+            unseal(b"plain", &key()),
+            Err(SealError::NotSealed)
+        ));
+        let sealed = seal(b"bonus", ["a.pdf"], &key()).unwrap();
+        for len in MAGIC.len()..MAGIC.len() + 9 {
+            assert!(matches!(
+                unseal(&sealed[..len], &key()),
+                Err(SealError::Truncated)
+            ));
+        }
+        let mut bad_name = MAGIC.to_vec();
+        bad_name.extend_from_slice(&[1, 0, 1, 0, 0xff]);
+        assert!(matches!(requires(&bad_name), Err(SealError::Malformed)));
+    }
+
+    #[test]
+    fn oversized_requirements_fail() {
+        let long = "a".repeat(usize::from(u16::MAX) + 1);
+        assert!(matches!(
+            seal(b"bonus", [long.as_str()], &key()),
+            Err(SealError::TooLong)
+        ));
+        let many = vec!["a.pdf"; usize::from(u16::MAX) + 1];
+        assert!(matches!(
+            seal(b"bonus", many, &key()),
+            Err(SealError::TooLong)
+        ));
+        let longest = "a".repeat(usize::from(u16::MAX));
+        let sealed = seal(b"bonus", [longest.as_str()], &key()).unwrap();
+        assert_eq!(requires(&sealed).unwrap(), [longest.as_str()]);
+    }
+}
@@ -0,0 +1,123 @@
+//! Player settings, persisted as RON.
+//! Natively they live in `settings.ron` in the working directory, on the web in local storage.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    controls::Controls,
+    k::{KEffects, KLook},
+};
+
+const SETTINGS_FILE: &str = "settings.ron";
+#[cfg(target_arch = "wasm32")]
+const STORAGE_KEY: &str = "stoned.settings";
+
+pub(super) fn plugin(app: &mut App) {
+    app.insert_resource(load())
+        .add_systems(Update, save.run_if(resource_changed::<Settings>));
+}
+
+#[derive(Resource, Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct Settings {
+    pub accessibility: Accessibility,
+    pub controls: Controls,
+    pub player: PlayerSettings,
+    pub graphics: Graphics,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct Accessibility {
+    /// Tones the K post process down, see [`crate::k::photosensitive_clamp`]
+    pub photosensitive_safe: bool,
+    /// The photosensitivity warning only shows on the first launch
+    pub warning_seen: bool,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct Graphics {
+    /// Parts of the K post process that are turned on
+    pub k_effects: KEffects,
+    pub k_look: KLook,
+}
+
+/// Camera and movement feel, applied live by the player
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct PlayerSettings {
+    /// Camera turn per pixel of mouse motion
+    pub mouse_sensitivity: f32,
+    /// Camera turn for the right stick at full tilt
+    pub gamepad_sensitivity: f32,
+    pub invert_y: bool,
+    /// Vertical field of view in degrees
+    pub fov: f32,
+    /// Crouch stays on until pressed again instead of while held
+    pub toggle_crouch: bool,
+    /// The camera bobs with the footsteps
+    pub head_bob: bool,
+    /// The camera dips when landing from a fall
+    pub landing_dip: bool,
+}
+
+impl Default for PlayerSettings {
+    fn default() -> Self {
+        Self {
+            mouse_sensitivity: 0.07,
+            gamepad_sensitivity: 4.0,
+            invert_y: false,
+            // same as bevy's default projection
+            fov: 45.0,
+            toggle_crouch: false,
+            head_bob: true,
+            landing_dip: true,
+        }
+    }
+}
+
+fn load() -> Settings {
+    let Some(ron) = read() else {
+        return Settings::default();
+    };
+    ron::de::from_str(&ron).unwrap_or_else(|e| {
+        warn!("ignoring broken {SETTINGS_FILE}: {e}");
+        Settings::default()
+    })
+}
+
+fn save(settings: Res<Settings>) {
+    match ron::ser::to_string_pretty(&*settings, default()) {
+        Ok(ron) => write(&ron),
+        Err(e) => error!("could not serialize settings: {e}"),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read() -> Option<String> {
+    std::fs::read_to_string(SETTINGS_FILE).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write(ron: &str) {
+    if let Err(e) = std::fs::write(SETTINGS_FILE, ron) {
+        error!("could not save {SETTINGS_FILE}: {e}");
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read() -> Option<String> {
+    let storage = web_sys::window()?.local_storage().ok()??;
+    storage.get_item(STORAGE_KEY).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write(ron: &str) {
+    let storage = web_sys::window().and_then(|w| w.local_storage().ok().flatten());
+    match storage.map(|s| s.set_item(STORAGE_KEY, ron)) {
+        Some(Ok(())) => {}
+        _ => error!("could not save {SETTINGS_FILE} to local storage"),
+    }
+}
@@ -0,0 +1,167 @@
+//! Sprinting. Holding [`Sprint`] while moving speeds the character controller up and drains
+//! [`Stamina`], running out leaves the player exhausted until it's partly back.
+//! Sprinting makes the trip stronger through [`KExertion`].
+
+use avian_pickup::actor::AvianPickupActorState;
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_ahoy::prelude::*;
+use bevy_enhanced_input::prelude::*;
+
+use crate::{
+    GameState,
+    k::KExertion,
+    widgets::{ProgressFill, StaminaBar, StaminaBarFill, stamina_bar, stamina_color},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<SprintConfig>()
+        .init_resource::<Stamina>()
+        .add_systems(Startup, spawn_bar)
+        .add_systems(
+            Update,
+            (sprint, show_bar)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, InputAction)]
+#[action_output(bool)]
+pub struct Sprint;
+
+/// Sprint tuning
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct SprintConfig {
+    /// Walking speed is multiplied by this
+    pub speed_multiplier: f32,
+    /// Seconds a full bar lasts
+    pub duration: f32,
+    /// Seconds to fill an empty bar
+    pub recovery: f32,
+    /// Seconds after sprinting before stamina comes back
+    pub recovery_delay: f32,
+    /// Stamina an exhausted player needs back before sprinting again, 0.0 - 1.0
+    pub recovered: f32,
+    /// Holding anything heavier than this rules sprinting out, kg
+    pub max_prop_mass: f32,
+    /// Seconds for [`KExertion`] to build up while sprinting, it fades twice as slow
+    pub exertion_time: f32,
+}
+
+impl Default for SprintConfig {
+    fn default() -> Self {
+        Self {
+            speed_multiplier: 1.6,
+            duration: 5.0,
+            recovery: 8.0,
+            recovery_delay: 1.0,
+            recovered: 0.3,
+            // a chair is fine, a filing cabinet is not
+            max_prop_mass: 15.0,
+            exertion_time: 3.0,
+        }
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct Stamina {
+    /// 0.0 - 1.0
+    pub value: f32,
+    /// Ran out, can't sprint until [`SprintConfig::recovered`]
+    pub exhausted: bool,
+    /// Seconds since the last sprint
+    rest: f32,
+    /// The controller's own speed, from before sprinting touched it
+    walk_speed: Option<f32>,
+}
+
+impl Default for Stamina {
+    fn default() -> Self {
+        Self {
+            value: 1.0,
+            exhausted: false,
+            rest: 0.0,
+            walk_speed: None,
+        }
+    }
+}
+
+fn spawn_bar(mut cmd: Commands) {
+    cmd.spawn(stamina_bar());
+}
+
+fn sprint(
+    time: Res<Time>,
+    config: Res<SprintConfig>,
+    mut stamina: ResMut<Stamina>,
+    mut exertion: ResMut<KExertion>,
+    controller: Single<&mut CharacterController>,
+    movement: Single<&Action<Movement>>,
+    sprinting: Single<&Action<Sprint>>,
+    actor: Option<Single<&AvianPickupActorState>>,
+    masses: Query<&ComputedMass>,
+) {
+    let dt = time.delta_secs();
+    let mut controller = controller.into_inner();
+    let walk_speed = *stamina.walk_speed.get_or_insert(controller.speed);
+
+    let heavy = actor.is_some_and(|actor| match **actor {
+        AvianPickupActorState::Holding(prop) => masses
+            .get(prop)
+            .is_ok_and(|mass| mass.value() > config.max_prop_mass),
+        _ => false,
+    });
+    let running = **sprinting
+        && **movement != Vec2::ZERO
+        && !heavy
+        && !stamina.exhausted
+        && stamina.value > 0.0;
+
+    if running {
+        stamina.rest = 0.0;
+        stamina.value = (stamina.value - dt / config.duration).max(0.0);
+        stamina.exhausted = stamina.value == 0.0;
+    } else {
+        stamina.rest += dt;
+        if stamina.rest >= config.recovery_delay {
+            stamina.value = (stamina.value + dt / config.recovery).min(1.0);
+        }
+        if stamina.value >= config.recovered {
+            stamina.exhausted = false;
+        }
+    }
+
+    let speed = if running {
+        walk_speed * config.speed_multiplier
+    } else {
+        walk_speed
+    };
+    if controller.speed != speed {
+        controller.speed = speed;
+    }
+
+    let (target, time_to_target) = if running {
+        (1.0, config.exertion_time)
+    } else {
+        (0.0, config.exertion_time * 2.0)
+    };
+    exertion.smooth_nudge(&target, 3.0 / time_to_target.max(f32::EPSILON), dt);
+}
+
+fn show_bar(
+    stamina: Res<Stamina>,
+    bar: Single<&mut Visibility, With<StaminaBar>>,
+    fill: Single<(&mut ProgressFill, &mut BackgroundColor), With<StaminaBarFill>>,
+) {
+    let mut visibility = bar.into_inner();
+    if stamina.value >= 1.0 {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    }
+    visibility.set_if_neq(Visibility::Inherited);
+    let (mut fill, mut color) = fill.into_inner();
+    fill.0 = stamina.value;
+    color.set_if_neq(BackgroundColor(stamina_color(stamina.exhausted)));
+}
@@ -0,0 +1,196 @@
+//! Hold-to-charge throwing. [`ChargeThrow`] charges while held, letting go fires bevy_ahoy's
+//! [`ThrowObject`] and then replaces the prop's velocity with one from the charge.
+//! Heavy props get a slower throw, see [`ThrowConfig`].
+
+use avian_pickup::actor::AvianPickupActorState;
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_ahoy::prelude::*;
+use bevy_enhanced_input::prelude::*;
+
+use crate::{
+    GameState,
+    widgets::{ProgressFill, ThrowMeter, ThrowMeterFill, throw_meter},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<ThrowConfig>()
+        .init_resource::<ThrowCharge>()
+        .add_systems(Startup, spawn_meter)
+        .add_systems(
+            Update,
+            (charge_throw, finish_throw, show_meter)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, InputAction)]
+#[action_output(bool)]
+pub struct ChargeThrow;
+
+/// Throw tuning
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct ThrowConfig {
+    /// Seconds to a full charge
+    pub charge_time: f32,
+    /// Speed of a tap, m/s
+    pub min_speed: f32,
+    /// Speed at full charge, m/s
+    pub max_speed: f32,
+    /// Most momentum a throw can give, kg m/s.
+    /// Props heavier than `max_impulse / speed` go slower than `speed`
+    pub max_impulse: f32,
+}
+
+impl Default for ThrowConfig {
+    fn default() -> Self {
+        Self {
+            charge_time: 0.8,
+            min_speed: 1.5,
+            max_speed: 12.0,
+            // a 40 kg crate still flies at 1 m/s, furniture barely tips over
+            max_impulse: 40.0,
+        }
+    }
+}
+
+impl ThrowConfig {
+    pub fn speed(&self, charge: f32, mass: f32) -> f32 {
+        let speed = self.min_speed.lerp(self.max_speed, charge.clamp(0.0, 1.0));
+        speed.min(self.max_impulse / mass.max(f32::EPSILON))
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+struct ThrowCharge {
+    /// 0.0 - 1.0, `None` while not charging
+    charge: Option<f32>,
+    /// Thrown, waiting for the pickup to let go of it
+    pending: Option<PendingThrow>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct PendingThrow {
+    prop: Entity,
+    speed: f32,
+    /// [`Time::elapsed_secs`] when it was thrown
+    at: f32,
+}
+
+/// Seconds the pickup gets to throw in the fixed schedule, a handful of fixed steps
+/// however fast or slow the frames are. Past that it didn't throw, and letting go later
+/// is a plain drop.
+const PENDING_TIME: f32 = 0.1;
+
+fn spawn_meter(mut cmd: Commands) {
+    cmd.spawn(throw_meter());
+}
+
+fn charge_throw(
+    mut cmd: Commands,
+    time: Res<Time>,
+    config: Res<ThrowConfig>,
+    mut throw: ResMut<ThrowCharge>,
+    charging: Single<&Action<ChargeThrow>>,
+    actor: Single<&AvianPickupActorState>,
+    masses: Query<&ComputedMass>,
+    throw_action: Query<Entity, With<Action<ThrowObject>>>,
+) {
+    let &AvianPickupActorState::Holding(prop) = *actor else {
+        throw.charge = None;
+        return;
+    };
+
+    if **charging {
+        throw.pending = None;
+        let charge = throw.charge.get_or_insert(0.0);
+        *charge = (*charge + time.delta_secs() / config.charge_time).min(1.0);
+        return;
+    }
+    let Some(charge) = throw.charge.take() else {
+        return;
+    };
+
+    let mass = masses.get(prop).map_or(1.0, |mass| mass.value());
+    throw.pending = Some(PendingThrow {
+        prop,
+        speed: config.speed(charge, mass),
+        at: time.elapsed_secs(),
+    });
+    for action in &throw_action {
+        cmd.entity(action)
+            .insert(ActionMock::once(ActionState::Fired, true));
+    }
+}
+
+/// The pickup throws with its own impulse, the velocity is swapped once it let go
+fn finish_throw(
+    time: Res<Time>,
+    mut throw: ResMut<ThrowCharge>,
+    actor: Single<&AvianPickupActorState>,
+    camera: Single<&GlobalTransform, With<CharacterControllerCameraOf>>,
+    mut props: Query<&mut LinearVelocity>,
+) {
+    let Some(pending) = throw.pending else {
+        return;
+    };
+    if time.elapsed_secs() - pending.at > PENDING_TIME {
+        throw.pending = None;
+        return;
+    }
+    if matches!(*actor, AvianPickupActorState::Holding(held) if *held == pending.prop) {
+        return;
+    }
+
+    throw.pending = None;
+    if let Ok(mut velocity) = props.get_mut(pending.prop) {
+        velocity.0 = camera.forward() * pending.speed;
+    }
+}
+
+fn show_meter(
+    throw: Res<ThrowCharge>,
+    meter: Single<&mut Visibility, With<ThrowMeter>>,
+    mut fill: Single<&mut ProgressFill, With<ThrowMeterFill>>,
+) {
+    let mut visibility = meter.into_inner();
+    match throw.charge {
+        Some(charge) => {
+            visibility.set_if_neq(Visibility::Inherited);
+            fill.0 = charge;
+        }
+        None => {
+            visibility.set_if_neq(Visibility::Hidden);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tap_throws_at_min_speed() {
+        let config = ThrowConfig::default();
+        assert_eq!(config.speed(0.0, 1.0), config.min_speed);
+    }
+
+    #[test]
+    fn full_charge_throws_at_max_speed() {
+        let config = ThrowConfig::default();
+        assert_eq!(config.speed(1.0, 1.0), config.max_speed);
+        assert_eq!(config.speed(2.0, 1.0), config.max_speed);
+    }
+
+    #[test]
+    fn heavy_props_are_capped_by_impulse() {
+        let config = ThrowConfig::default();
+        let mass = 10.0;
+        assert!(mass > config.max_impulse / config.max_speed);
+        assert_eq!(config.speed(1.0, mass), config.max_impulse / mass);
+        // light enough for a tap to be unaffected
+        assert_eq!(config.speed(0.0, mass), config.min_speed);
+    }
+}
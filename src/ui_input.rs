@@ -0,0 +1,339 @@
+//! Gamepad and keyboard navigation for the UI.
+//!
+//! Clickable UI is marked [`Focusable`], its handlers observe [`UiActivate`] instead of
+//! `Pointer<Click>`. A click activates what was clicked, confirm on the [`UiInput`] context
+//! activates the focused entity and cancel the [`Cancels`] one, so every handler works
+//! with mouse and controller alike. Only the top layer of UI, by [`GlobalZIndex`],
+//! can be focused, so a dialogue under the controls screen waits its turn.
+//!
+//! Dialogues also advance from the keyboard through the [`DialogueInput`] context,
+//! which is active while a [`DialogueOverlay`] exists.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_enhanced_input::prelude::{Press, *};
+
+use crate::{
+    controls::ControlsScreen,
+    player::BlocksPlayer,
+    widgets::{DialogueOverlay, focus_outline},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_input_context::<UiInput>()
+        .add_input_context::<DialogueInput>()
+        .init_resource::<UiFocus>()
+        .init_resource::<UiPressed>()
+        .add_systems(Startup, (spawn_ui_input, spawn_dialogue_input))
+        .add_systems(
+            Update,
+            (
+                (activate_ui_input, keep_focus).chain(),
+                activate_dialogue_input,
+            ),
+        )
+        .add_systems(Last, |mut pressed: ResMut<UiPressed>| pressed.0 = false)
+        .add_observer(activate_on_click)
+        .add_observer(press_ui)
+        .add_observer(confirm)
+        .add_observer(cancel)
+        .add_observer(navigate)
+        .add_observer(advance_dialogue);
+}
+
+/// Active while there's UI to navigate
+#[derive(Component, Default)]
+pub struct UiInput;
+
+#[derive(Debug, InputAction)]
+#[action_output(bool)]
+struct UiConfirm;
+
+#[derive(Debug, InputAction)]
+#[action_output(bool)]
+struct UiCancel;
+
+#[derive(Debug, InputAction)]
+#[action_output(Vec2)]
+struct UiNavigate;
+
+/// Active while a dialogue is on screen
+#[derive(Component, Default)]
+pub struct DialogueInput;
+
+#[derive(Debug, InputAction)]
+#[action_output(bool)]
+struct AdvanceDialogue;
+
+/// A click, or confirm or cancel on the focused UI. Propagates up like a click does
+#[derive(EntityEvent, Clone, Copy, Debug)]
+#[entity_event(propagate, auto_propagate)]
+pub struct UiActivate {
+    pub entity: Entity,
+}
+
+/// UI that reacts to [`UiActivate`]
+#[derive(Component, Default)]
+pub struct Focusable;
+
+/// Activated by cancel, like a close button
+#[derive(Component, Default)]
+#[require(Focusable)]
+pub struct Cancels;
+
+#[derive(Resource, Default, Debug)]
+pub struct UiFocus(pub Option<Entity>);
+
+/// A press landed on UI this frame, so it wasn't meant for the game.
+/// Clicks only fire on release, too late for anything reacting to the press
+#[derive(Resource, Default, Debug)]
+pub struct UiPressed(pub bool);
+
+/// Run condition for things that shouldn't react to presses on UI
+pub fn ui_pressed(pressed: Res<UiPressed>) -> bool {
+    pressed.0
+}
+
+fn spawn_ui_input(mut cmd: Commands) {
+    cmd.spawn((
+        Name::new("ui input"),
+        UiInput,
+        ContextActivity::<UiInput>::INACTIVE,
+        actions!(UiInput[
+            (
+                Action::<UiConfirm>::new(),
+                Press::default(),
+                bindings![GamepadButton::South],
+            ),
+            (
+                Action::<UiCancel>::new(),
+                Press::default(),
+                bindings![GamepadButton::East],
+            ),
+            (
+                Action::<UiNavigate>::new(),
+                Press::default(),
+                Bindings::spawn((
+                    Cardinal::dpad(),
+                    Cardinal::arrows(),
+                    Axial::left_stick().with(DeadZone::default()),
+                )),
+            ),
+        ]),
+    ));
+}
+
+/// What [`UiFocus`] can move between
+#[derive(SystemParam)]
+struct FocusLayer<'w, 's> {
+    focusables: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static UiGlobalTransform,
+            &'static InheritedVisibility,
+            Has<Cancels>,
+        ),
+        With<Focusable>,
+    >,
+    parents: Query<'w, 's, &'static ChildOf>,
+    layers: Query<'w, 's, &'static GlobalZIndex>,
+}
+
+struct Candidate {
+    entity: Entity,
+    position: Vec2,
+    cancels: bool,
+}
+
+impl FocusLayer<'_, '_> {
+    fn layer(&self, entity: Entity) -> i32 {
+        std::iter::once(entity)
+            .chain(self.parents.iter_ancestors(entity))
+            .find_map(|e| self.layers.get(e).ok())
+            .map_or(0, |z| z.0)
+    }
+
+    /// Visible focusables of the top layer, top to bottom and left to right
+    fn candidates(&self) -> Vec<Candidate> {
+        let visible: Vec<_> = self
+            .focusables
+            .iter()
+            .filter(|(_, _, visibility, _)| visibility.get())
+            .map(|(entity, transform, _, cancels)| {
+                let candidate = Candidate {
+                    entity,
+                    position: transform.translation,
+                    cancels,
+                };
+                (self.layer(entity), candidate)
+            })
+            .collect();
+        let Some(top) = visible.iter().map(|(layer, _)| *layer).max() else {
+            return Vec::new();
+        };
+
+        let mut candidates: Vec<_> = visible
+            .into_iter()
+            .filter(|(layer, _)| *layer == top)
+            .map(|(_, candidate)| candidate)
+            .collect();
+        candidates.sort_by(|a, b| {
+            a.position
+                .y
+                .total_cmp(&b.position.y)
+                .then(a.position.x.total_cmp(&b.position.x))
+        });
+        candidates
+    }
+}
+
+/// Forwards clicks, only on the clicked entity since clicks propagate too
+fn activate_on_click(on: On<Pointer<Click>>, mut cmd: Commands) {
+    if on.original_event_target() == on.entity {
+        cmd.trigger(UiActivate { entity: on.entity });
+    }
+}
+
+/// Presses propagate up from the text to the button or panel
+fn press_ui(
+    on: On<Pointer<bevy::picking::events::Press>>,
+    ui: Query<(), Or<(With<Focusable>, With<BlocksPlayer>)>>,
+    mut pressed: ResMut<UiPressed>,
+) {
+    if ui.contains(on.entity) {
+        pressed.0 = true;
+    }
+}
+
+fn activate_ui_input(
+    mut cmd: Commands,
+    layer: FocusLayer,
+    context: Single<(Entity, &ContextActivity<UiInput>)>,
+    screens: Query<&ControlsScreen>,
+) {
+    // the buttons are being rebound, don't press them too
+    let capturing = screens.iter().any(ControlsScreen::is_capturing);
+    let active = !capturing && !layer.candidates().is_empty();
+    let (entity, activity) = context.into_inner();
+    if **activity != active {
+        cmd.entity(entity).insert(if active {
+            ContextActivity::<UiInput>::ACTIVE
+        } else {
+            ContextActivity::<UiInput>::INACTIVE
+        });
+    }
+}
+
+/// Keeps the focus on the top layer and outlines it
+fn keep_focus(
+    mut cmd: Commands,
+    layer: FocusLayer,
+    mut focus: ResMut<UiFocus>,
+    mut outlined: Local<Option<Entity>>,
+) {
+    let candidates = layer.candidates();
+    if !candidates.iter().any(|c| Some(c.entity) == focus.0) {
+        focus.0 = candidates.first().map(|c| c.entity);
+    }
+
+    // an outline around a whole screen overlay only gets in the way
+    let outline = focus.0.filter(|_| candidates.len() > 1);
+    if *outlined == outline {
+        return;
+    }
+    if let Some(old) = outlined.take() {
+        cmd.entity(old).try_remove::<Outline>();
+    }
+    if let Some(new) = outline {
+        cmd.entity(new).try_insert(focus_outline());
+    }
+    *outlined = outline;
+}
+
+fn confirm(_: On<Fire<UiConfirm>>, mut cmd: Commands, focus: Res<UiFocus>) {
+    if let Some(entity) = focus.0 {
+        cmd.trigger(UiActivate { entity });
+    }
+}
+
+fn cancel(_: On<Fire<UiCancel>>, mut cmd: Commands, layer: FocusLayer) {
+    if let Some(candidate) = layer.candidates().iter().find(|c| c.cancels) {
+        cmd.trigger(UiActivate {
+            entity: candidate.entity,
+        });
+    }
+}
+
+/// Moves to the closest candidate roughly in the pressed direction
+fn navigate(on: On<Fire<UiNavigate>>, layer: FocusLayer, mut focus: ResMut<UiFocus>) {
+    let candidates = layer.candidates();
+    let Some(current) = candidates.iter().find(|c| Some(c.entity) == focus.0) else {
+        return;
+    };
+    // ui positions grow downwards
+    let direction = Vec2::new(on.value.x, -on.value.y).normalize_or_zero();
+
+    let next = candidates
+        .iter()
+        .filter(|c| c.entity != current.entity)
+        .filter_map(|c| {
+            let offset = c.position - current.position;
+            let distance = offset.length();
+            // within 60 degrees of the direction
+            (offset.dot(direction) > distance * 0.5).then_some((c.entity, distance))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+    if let Some((entity, _)) = next {
+        focus.0 = Some(entity);
+    }
+}
+
+fn spawn_dialogue_input(mut cmd: Commands) {
+    cmd.spawn((
+        Name::new("dialogue input"),
+        DialogueInput,
+        ContextActivity::<DialogueInput>::INACTIVE,
+        actions!(
+            DialogueInput[(
+                Action::<AdvanceDialogue>::new(),
+                Press::default(),
+                bindings![KeyCode::Space, KeyCode::Enter, KeyCode::KeyE],
+            )]
+        ),
+    ));
+}
+
+fn activate_dialogue_input(
+    mut cmd: Commands,
+    dialogues: Query<(), With<DialogueOverlay>>,
+    context: Single<(Entity, &ContextActivity<DialogueInput>)>,
+) {
+    let active = !dialogues.is_empty();
+    let (entity, activity) = context.into_inner();
+    if **activity != active {
+        cmd.entity(entity).insert(if active {
+            ContextActivity::<DialogueInput>::ACTIVE
+        } else {
+            ContextActivity::<DialogueInput>::INACTIVE
+        });
+    }
+}
+
+/// Same as clicking the dialogue, unless something is open on top of it
+fn advance_dialogue(
+    _: On<Fire<AdvanceDialogue>>,
+    mut cmd: Commands,
+    layer: FocusLayer,
+    dialogues: Query<(), With<DialogueOverlay>>,
+) {
+    if let Some(candidate) = layer
+        .candidates()
+        .iter()
+        .find(|c| dialogues.contains(c.entity))
+    {
+        cmd.trigger(UiActivate {
+            entity: candidate.entity,
+        });
+    }
+}
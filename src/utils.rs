@@ -16,11 +16,17 @@ use bevy::{
     window::{CursorGrabMode, CursorOptions},
 };
 use bevy_ahoy::{CharacterControllerOutput, CharacterControllerState, prelude::*};
-use bevy_enhanced_input::prelude::{Release, *};
+use bevy_enhanced_input::prelude::*;
 use bevy_fix_cursor_unlock_web::{FixPointerUnlockPlugin, ForceUnlockCursor};
 use bevy_framepace::FramepacePlugin;
 use bevy_mod_mipmap_generator::{MipmapGeneratorPlugin, generate_mipmaps};
 
+use crate::{
+    controls::{debug_actions, open_controls},
+    settings::Settings,
+    widgets::ControlsText,
+};
+
 pub(super) struct ExampleUtilPlugin;
 
 impl Plugin for ExampleUtilPlugin {
@@ -126,45 +132,33 @@ fn update_debug_text(
 #[reflect(Component)]
 struct DebugText;
 
-fn setup_ui(mut commands: Commands) {
+fn setup_ui(mut commands: Commands, settings: Res<Settings>) {
     commands.spawn((
         Node::default(),
         Text::default(),
         Visibility::Hidden,
         DebugText,
     ));
-    commands.spawn((
-        Name::new("controls"),
-        Node {
-            justify_self: JustifySelf::End,
-            justify_content: JustifyContent::End,
-            align_self: AlignSelf::End,
-            padding: UiRect::all(px(10.0)),
-            ..default()
-        },
-        Text::new(
-            "Controls:\nWASD: move\nSpace: jump\nCtrl: crouch\nEsc: free mouse\nE: pickup object\nQ: drop object",
-        ),
-    ));
-    commands.spawn((
-        DebugInput,
-        actions!(DebugInput[
-            (
-                Action::<Reset>::new(),
-                bindings![KeyCode::KeyR, GamepadButton::Select],
-                Release::default(),
-            ),
-            (
-                Action::<ToggleDebug>::new(),
-                bindings![KeyCode::Backquote, GamepadButton::Start],
-                Release::default(),
-            ),
-        ]),
-    ));
+    commands
+        .spawn((
+            Name::new("controls"),
+            ControlsText,
+            Pickable::default(),
+            Node {
+                justify_self: JustifySelf::End,
+                justify_content: JustifyContent::End,
+                align_self: AlignSelf::End,
+                padding: UiRect::all(px(10.0)),
+                ..default()
+            },
+            Text::new(settings.controls.text()),
+        ))
+        .observe(open_controls);
+    commands.spawn((DebugInput, debug_actions(&settings.controls)));
 }
 
 #[derive(Component, Default)]
-struct DebugInput;
+pub(super) struct DebugInput;
 
 #[derive(Debug, InputAction)]
 #[action_output(bool)]
@@ -0,0 +1,189 @@
+use bevy::{
+    asset::{LoadState, RenderAssetUsages},
+    image::{CompressedImageFormats, ImageSampler, ImageType},
+    prelude::*,
+};
+
+use crate::{
+    blob::Blob,
+    document::Document,
+    ui_input::UiActivate,
+    widgets::{ViewerBody, ViewerPageLabel, document_viewer, viewer_image_page, viewer_text_page},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(Update, (load_document, show_page).chain())
+        .add_observer(open_document);
+}
+
+/// Opens the viewer overlay for a blob, it fills itself in once the blob is loaded
+#[derive(Event)]
+pub struct OpenDocument {
+    pub blob: Handle<Blob>,
+    /// shown as the viewer title
+    pub name: String,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ViewerAction {
+    PrevPage,
+    NextPage,
+    Download,
+    Close,
+}
+
+#[derive(Component)]
+pub struct ViewerButton(pub ViewerAction);
+
+#[derive(Component)]
+pub struct DocumentViewer {
+    pub blob: Handle<Blob>,
+    pub name: String,
+    pub page: usize,
+    pub pages: Vec<ViewerPage>,
+}
+
+pub enum ViewerPage {
+    Text(String),
+    Image(Handle<Image>),
+}
+
+/// Replaces the open viewer, there's only ever one
+fn open_document(
+    on: On<OpenDocument>,
+    mut cmd: Commands,
+    viewers: Query<Entity, With<DocumentViewer>>,
+) {
+    for viewer in &viewers {
+        cmd.entity(viewer).despawn();
+    }
+    cmd.spawn((
+        DocumentViewer {
+            blob: on.blob.clone(),
+            name: on.name.clone(),
+            page: 0,
+            pages: Vec::new(),
+        },
+        document_viewer(on.name.clone()),
+    ))
+    .observe(on_viewer_click);
+}
+
+/// Observes the viewer, so the clicked button belongs to `on.entity`
+fn on_viewer_click(
+    on: On<UiActivate>,
+    mut cmd: Commands,
+    buttons: Query<&ViewerButton>,
+    mut viewers: Query<&mut DocumentViewer>,
+    blobs: Res<Assets<Blob>>,
+) {
+    let Ok(ViewerButton(action)) = buttons.get(on.original_event_target()) else {
+        return;
+    };
+    let Ok(mut viewer) = viewers.get_mut(on.entity) else {
+        return;
+    };
+    match action {
+        ViewerAction::PrevPage => viewer.page = viewer.page.saturating_sub(1),
+        ViewerAction::NextPage => {
+            viewer.page = (viewer.page + 1).min(viewer.pages.len().saturating_sub(1))
+        }
+        ViewerAction::Download => match blobs.get(&viewer.blob) {
+            Some(blob) if blob.is_sealed() => warn!("{} is still sealed", viewer.name),
+            Some(blob) => blob.download(blob.file_name()),
+            None => warn!("{} is not loaded yet", viewer.name),
+        },
+        ViewerAction::Close => cmd.entity(on.entity).despawn(),
+    }
+}
+
+fn load_document(
+    mut viewers: Query<&mut DocumentViewer>,
+    mut events: MessageReader<AssetEvent<Blob>>,
+    ass: Res<AssetServer>,
+    blobs: Res<Assets<Blob>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for event in events.read() {
+        // e.g. a sealed blob that just got unlocked
+        if let AssetEvent::Modified { id } = event {
+            for mut viewer in &mut viewers {
+                if viewer.blob.id() == *id {
+                    viewer.pages.clear();
+                    viewer.page = 0;
+                }
+            }
+        }
+    }
+
+    for mut viewer in &mut viewers {
+        if !viewer.pages.is_empty() {
+            continue;
+        }
+        let Some(blob) = blobs.get(&viewer.blob) else {
+            if let Some(LoadState::Failed(e)) = ass.get_load_state(viewer.blob.id()) {
+                warn!("could not load {}: {e}", viewer.name);
+                viewer.pages = vec![ViewerPage::Text("This file could not be loaded.".into())];
+            }
+            continue;
+        };
+
+        if blob.is_sealed() {
+            viewer.pages = vec![ViewerPage::Text(
+                "This file is sealed. Collect the other files to unlock it.".into(),
+            )];
+            continue;
+        }
+
+        viewer.pages = match Document::parse(blob.bytes()) {
+            Some(Document::Text(pages)) => pages.into_iter().map(ViewerPage::Text).collect(),
+            Some(Document::Image(ext)) => match Image::from_buffer(
+                blob.bytes(),
+                ImageType::Extension(ext),
+                CompressedImageFormats::NONE,
+                true,
+                ImageSampler::Default,
+                RenderAssetUsages::default(),
+            ) {
+                Ok(image) => vec![ViewerPage::Image(images.add(image))],
+                Err(e) => {
+                    warn!("could not decode {}: {e}", viewer.name);
+                    vec![ViewerPage::Text(
+                        "This image can't be shown, download it instead.".into(),
+                    )]
+                }
+            },
+            None => vec![ViewerPage::Text(
+                "This file can't be shown, download it instead.".into(),
+            )],
+        };
+    }
+}
+
+fn show_page(
+    mut cmd: Commands,
+    viewers: Query<(Entity, &DocumentViewer), Changed<DocumentViewer>>,
+    children: Query<&Children>,
+    bodies: Query<(), With<ViewerBody>>,
+    mut labels: Query<&mut Text, With<ViewerPageLabel>>,
+) {
+    for (entity, viewer) in &viewers {
+        let Some(page) = viewer.pages.get(viewer.page) else {
+            continue;
+        };
+        for descendant in children.iter_descendants(entity) {
+            if let Ok(mut label) = labels.get_mut(descendant) {
+                label.0 = format!("page {}/{}", viewer.page + 1, viewer.pages.len());
+            }
+            if !bodies.contains(descendant) {
+                continue;
+            }
+            let mut body = cmd.entity(descendant);
+            body.despawn_children();
+            match page {
+                ViewerPage::Text(text) => body.with_child(viewer_text_page(text.clone())),
+                ViewerPage::Image(image) => body.with_child(viewer_image_page(image.clone())),
+            };
+        }
+    }
+}
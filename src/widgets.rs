@@ -1,13 +1,21 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, ui::UiSystems};
 use bevy_easy_gif::{GifAsset, GifNode};
 
-use crate::{Progress, player::EnablePlayer};
+use crate::{
+    InspectInfo, Progress,
+    controls::{Control, ControlsAction, ControlsButton, Slot},
+    k::WarningChoice,
+    player::BlocksPlayer,
+    ui_input::{Cancels, Focusable, UiActivate},
+    viewer::{ViewerAction, ViewerButton},
+};
 
 pub(super) fn plugin(app: &mut App) {
     app.add_systems(
         Update,
         (animate_fadein, update_timer, dialogue_typewriter_system),
-    );
+    )
+    .add_systems(PostUpdate, fill_progress_bars.before(UiSystems::Layout));
 }
 
 #[derive(Component)]
@@ -34,6 +42,61 @@ pub struct DialogueSpeakerName;
 #[derive(Component)]
 pub struct DialoguePortrait;
 
+#[derive(Component)]
+pub struct LoadingBar;
+
+#[derive(Component)]
+pub struct LoadingStatus;
+
+#[derive(Component)]
+pub struct ViewerBody;
+
+#[derive(Component)]
+pub struct ViewerPageLabel;
+
+#[derive(Component)]
+pub struct ControlsText;
+
+#[derive(Component)]
+pub struct ControlsList;
+
+#[derive(Component)]
+pub struct ControlsStatus;
+
+#[derive(Component)]
+pub struct OptionsList;
+
+#[derive(Component)]
+pub struct InteractPrompt;
+
+#[derive(Component)]
+pub struct InteractPromptText;
+
+#[derive(Component)]
+pub struct InspectPanel;
+
+#[derive(Component)]
+pub struct ThrowMeter;
+
+#[derive(Component)]
+pub struct ThrowMeterFill;
+
+#[derive(Component)]
+pub struct StaminaBar;
+
+#[derive(Component)]
+pub struct StaminaBarFill;
+
+#[derive(Component)]
+pub struct InteractProgress;
+
+#[derive(Component)]
+pub struct InteractProgressFill;
+
+/// How full a [`progress_bar`] is, 0.0 - 1.0. Goes on the fill
+#[derive(Component, Default)]
+pub struct ProgressFill(pub f32);
+
 const SOULS_RED: Color = Color::srgba(0.54, 0.07, 0.07, 1.0); // #8a1212
 
 const BAR_COLOR: Color = Color::srgba(0.35, 0.04, 0.04, 0.6);
@@ -57,6 +120,8 @@ const PANEL_SHADOW: Color = Color::srgba(0.0, 0.0, 0.0, 0.60);
 const SPEAKER_COLOR: Color = Color::srgb(0.93, 0.79, 0.39);
 const BODY_COLOR: Color = Color::srgb(0.88, 0.88, 0.90);
 
+const BUTTON_BG: Color = Color::srgba(0.16, 0.14, 0.18, 0.95);
+
 const PORTRAIT_SIZE: f32 = 96.0;
 const PORTRAIT_BORDER: f32 = 3.0;
 
@@ -78,8 +143,7 @@ pub fn timer() -> impl Bundle {
     )
 }
 
-pub fn dismiss_ui(on: On<Pointer<Click>>, mut cmd: Commands) {
-    cmd.trigger(EnablePlayer);
+pub fn dismiss_ui(on: On<UiActivate>, mut cmd: Commands) {
     cmd.entity(on.entity).despawn();
 }
 
@@ -90,6 +154,8 @@ pub fn dialogue_box(
 ) -> impl Bundle {
     (
         DialogueOverlay,
+        BlocksPlayer,
+        Cancels,
         Pickable {
             should_block_lower: true,
             is_hoverable: true,
@@ -113,6 +179,634 @@ pub fn dialogue_box(
     )
 }
 
+pub fn loading_screen() -> impl Bundle {
+    (
+        BlocksPlayer,
+        Focusable,
+        Pickable::default(),
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            left: Val::Px(0.0),
+            top: Val::Px(0.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(18.0),
+            ..default()
+        },
+        GlobalZIndex(1100),
+        BackgroundColor(BG),
+        children![
+            section_heading("LOADING"),
+            progress_bar((), LoadingBar, Vec2::new(420.0, 6.0), HEADING_COLOR),
+            (
+                LoadingStatus,
+                Text::default(),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(HINT_COLOR),
+                TextLayout::new_with_justify(Justify::Center),
+            ),
+        ],
+    )
+}
+
+/// A bordered bar of `size` px, `marker` goes on the frame and `fill_marker` on the fill.
+/// Set the fill's [`ProgressFill`] to move it
+pub fn progress_bar(
+    marker: impl Bundle,
+    fill_marker: impl Bundle,
+    size: Vec2,
+    color: Color,
+) -> impl Bundle {
+    (
+        marker,
+        Node {
+            width: Val::Px(size.x),
+            height: Val::Px(size.y),
+            border: UiRect::all(Val::Px(1.0)),
+            ..default()
+        },
+        BorderColor::all(PANEL_BORDER),
+        children![(
+            fill_marker,
+            ProgressFill::default(),
+            Node {
+                width: Val::Percent(0.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            BackgroundColor(color),
+        )],
+    )
+}
+
+fn fill_progress_bars(mut fills: Query<(&ProgressFill, &mut Node), Changed<ProgressFill>>) {
+    for (fill, mut node) in &mut fills {
+        node.width = Val::Percent(fill.0.clamp(0.0, 1.0) * 100.0);
+    }
+}
+
+pub fn document_viewer(title: impl Into<String>) -> impl Bundle {
+    (
+        BlocksPlayer,
+        Pickable {
+            should_block_lower: true,
+            is_hoverable: true,
+        },
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            left: Val::Px(0.0),
+            top: Val::Px(0.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            padding: UiRect::all(Val::Px(32.0)),
+            ..default()
+        },
+        // above dialogues so the file is read before anyone talks about it
+        GlobalZIndex(950),
+        BackgroundColor(OVERLAY_BG),
+        children![(
+            Node {
+                width: Val::Percent(100.0),
+                max_width: Val::Px(820.0),
+                height: Val::Percent(100.0),
+                padding: UiRect::all(Val::Px(18.0)),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(12.0),
+                border: UiRect::all(Val::Px(1.0)),
+                border_radius: BorderRadius::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(PANEL_BG),
+            BorderColor::all(PANEL_BORDER),
+            children![
+                (
+                    Text::new(title),
+                    TextFont {
+                        font_size: 22.0,
+                        ..default()
+                    },
+                    TextColor(SPEAKER_COLOR),
+                ),
+                (
+                    ViewerBody,
+                    Node {
+                        flex_grow: 1.0,
+                        justify_content: JustifyContent::Center,
+                        overflow: Overflow::clip(),
+                        ..default()
+                    },
+                ),
+                (
+                    Node {
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(10.0),
+                        ..default()
+                    },
+                    children![
+                        viewer_button("<", ViewerAction::PrevPage),
+                        (
+                            ViewerPageLabel,
+                            Text::new("loading..."),
+                            TextFont {
+                                font_size: 16.0,
+                                ..default()
+                            },
+                            TextColor(HINT_COLOR),
+                        ),
+                        viewer_button(">", ViewerAction::NextPage),
+                        spacer_x(),
+                        viewer_button("download original", ViewerAction::Download),
+                        (viewer_button("close", ViewerAction::Close), Cancels),
+                    ],
+                ),
+            ],
+        )],
+    )
+}
+
+pub fn viewer_text_page(text: impl Into<String>) -> impl Bundle {
+    (
+        Text::new(text),
+        TextFont {
+            font_size: 15.0,
+            ..default()
+        },
+        TextColor(BODY_COLOR),
+        Node {
+            width: Val::Percent(100.0),
+            ..default()
+        },
+    )
+}
+
+pub fn viewer_image_page(image: Handle<Image>) -> impl Bundle {
+    (
+        ImageNode::new(image),
+        Node {
+            max_width: Val::Percent(100.0),
+            max_height: Val::Percent(100.0),
+            ..default()
+        },
+    )
+}
+
+fn viewer_button(label: impl Into<String>, action: ViewerAction) -> impl Bundle {
+    (
+        ViewerButton(action),
+        Focusable,
+        Pickable::default(),
+        Node {
+            padding: UiRect::axes(Val::Px(12.0), Val::Px(6.0)),
+            border: UiRect::all(Val::Px(1.0)),
+            border_radius: BorderRadius::all(Val::Px(4.0)),
+            ..default()
+        },
+        BackgroundColor(BUTTON_BG),
+        BorderColor::all(PANEL_BORDER),
+        children![(
+            Text::new(label),
+            TextFont {
+                font_size: 16.0,
+                ..default()
+            },
+            TextColor(NAME_COLOR),
+        )],
+    )
+}
+
+pub fn controls_screen() -> impl Bundle {
+    (
+        BlocksPlayer,
+        Pickable {
+            should_block_lower: true,
+            is_hoverable: true,
+        },
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            left: Val::Px(0.0),
+            top: Val::Px(0.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        GlobalZIndex(960),
+        BackgroundColor(OVERLAY_BG),
+        children![(
+            Node {
+                width: Val::Px(560.0),
+                padding: UiRect::all(Val::Px(18.0)),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(12.0),
+                border: UiRect::all(Val::Px(1.0)),
+                border_radius: BorderRadius::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(PANEL_BG),
+            BorderColor::all(PANEL_BORDER),
+            children![
+                section_heading("CONTROLS"),
+                (
+                    ControlsList,
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(4.0),
+                        ..default()
+                    },
+                ),
+                (
+                    OptionsList,
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(4.0),
+                        ..default()
+                    },
+                ),
+                (
+                    ControlsStatus,
+                    Text::default(),
+                    TextFont {
+                        font_size: 14.0,
+                        ..default()
+                    },
+                    TextColor(HINT_COLOR),
+                ),
+                (
+                    Node {
+                        flex_direction: FlexDirection::Row,
+                        column_gap: Val::Px(10.0),
+                        ..default()
+                    },
+                    children![
+                        spacer_x(),
+                        controls_button("reset to defaults", ControlsAction::Defaults),
+                        (controls_button("close", ControlsAction::Close), Cancels),
+                    ],
+                ),
+            ],
+        )],
+    )
+}
+
+/// `control` is `None` for rows that can't be rebound
+pub fn controls_row(
+    label: impl Into<String>,
+    keyboard: String,
+    gamepad: String,
+    control: Option<Control>,
+) -> impl Bundle {
+    (
+        Node {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            column_gap: Val::Px(10.0),
+            ..default()
+        },
+        Children::spawn((
+            Spawn((
+                Text::new(label),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(BODY_COLOR),
+                Node {
+                    flex_grow: 1.0,
+                    ..default()
+                },
+            )),
+            SpawnWith(move |parent: &mut ChildSpawner| {
+                for (text, slot) in [(keyboard, Slot::Keyboard), (gamepad, Slot::Gamepad)] {
+                    match control {
+                        Some(control) => {
+                            parent.spawn(controls_button(
+                                text,
+                                ControlsAction::Rebind(control, slot),
+                            ));
+                        }
+                        None => {
+                            parent.spawn(controls_binding_text(text));
+                        }
+                    }
+                }
+            }),
+        )),
+    )
+}
+
+/// Row of a setting on the controls screen, the label carries the current value
+pub fn option_row(label: String, buttons: Vec<(String, ControlsAction)>) -> impl Bundle {
+    (
+        Node {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            column_gap: Val::Px(10.0),
+            ..default()
+        },
+        Children::spawn((
+            Spawn((
+                Text::new(label),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(BODY_COLOR),
+                Node {
+                    flex_grow: 1.0,
+                    ..default()
+                },
+            )),
+            SpawnWith(move |parent: &mut ChildSpawner| {
+                for (text, action) in buttons {
+                    parent.spawn(controls_button(text, action));
+                }
+            }),
+        )),
+    )
+}
+
+fn controls_binding_text(text: impl Into<String>) -> impl Bundle {
+    (
+        Node {
+            min_width: Val::Px(130.0),
+            padding: UiRect::axes(Val::Px(12.0), Val::Px(6.0)),
+            ..default()
+        },
+        children![(
+            Text::new(text),
+            TextFont {
+                font_size: 16.0,
+                ..default()
+            },
+            TextColor(HINT_COLOR),
+        )],
+    )
+}
+
+fn controls_button(label: impl Into<String>, action: ControlsAction) -> impl Bundle {
+    (
+        ControlsButton(action),
+        Focusable,
+        Pickable::default(),
+        Node {
+            min_width: Val::Px(130.0),
+            padding: UiRect::axes(Val::Px(12.0), Val::Px(6.0)),
+            border: UiRect::all(Val::Px(1.0)),
+            border_radius: BorderRadius::all(Val::Px(4.0)),
+            ..default()
+        },
+        BackgroundColor(BUTTON_BG),
+        BorderColor::all(PANEL_BORDER),
+        children![(
+            Text::new(label),
+            TextFont {
+                font_size: 16.0,
+                ..default()
+            },
+            TextColor(NAME_COLOR),
+        )],
+    )
+}
+
+/// Marks the UI a gamepad has focused
+pub fn focus_outline() -> Outline {
+    Outline::new(Val::Px(2.0), Val::Px(2.0), HEADING_COLOR)
+}
+
+/// Below the crosshair, hidden until there's something to interact with
+pub fn interact_prompt() -> impl Bundle {
+    (
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        children![(
+            InteractPrompt,
+            Visibility::Hidden,
+            Node {
+                margin: UiRect::top(Val::Px(72.0)),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(6.0),
+                ..default()
+            },
+            children![
+                (
+                    InteractPromptText,
+                    Text::default(),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(NAME_COLOR),
+                    TextShadow::default(),
+                ),
+                progress_bar(
+                    (InteractProgress, Visibility::Hidden),
+                    InteractProgressFill,
+                    Vec2::new(96.0, 4.0),
+                    HEADING_COLOR,
+                ),
+            ],
+        )],
+    )
+}
+
+/// Under the crosshair while a throw is charged
+pub fn throw_meter() -> impl Bundle {
+    (
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            padding: UiRect::top(Val::Px(48.0)),
+            ..default()
+        },
+        children![progress_bar(
+            (ThrowMeter, Visibility::Hidden),
+            ThrowMeterFill,
+            Vec2::new(64.0, 4.0),
+            SOULS_RED,
+        )],
+    )
+}
+
+/// Bottom of the screen, shown while stamina isn't full
+pub fn stamina_bar() -> impl Bundle {
+    (
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::FlexEnd,
+            padding: UiRect::bottom(Val::Px(32.0)),
+            ..default()
+        },
+        children![progress_bar(
+            (StaminaBar, Visibility::Hidden, BackgroundColor(PANEL_BG)),
+            StaminaBarFill,
+            Vec2::new(200.0, 6.0),
+            TIMER_COLOR,
+        )],
+    )
+}
+
+/// Red while the player is out of breath
+pub fn stamina_color(exhausted: bool) -> Color {
+    if exhausted { SOULS_RED } else { TIMER_COLOR }
+}
+
+/// Description of an inspected prop, on the right so the prop stays visible
+pub fn inspect_panel(info: &InspectInfo) -> impl Bundle {
+    (
+        InspectPanel,
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(32.0),
+            top: Val::Percent(25.0),
+            width: Val::Px(340.0),
+            padding: UiRect::all(Val::Px(18.0)),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(10.0),
+            border: UiRect::all(Val::Px(1.0)),
+            border_radius: BorderRadius::all(Val::Px(6.0)),
+            ..default()
+        },
+        BackgroundColor(PANEL_BG),
+        BorderColor::all(PANEL_BORDER),
+        BoxShadow(vec![ShadowStyle {
+            color: PANEL_SHADOW,
+            x_offset: Val::Px(0.0),
+            y_offset: Val::Px(4.0),
+            spread_radius: Val::Px(2.0),
+            blur_radius: Val::Px(24.0),
+        }]),
+        children![
+            (
+                Text::new(info.title.clone()),
+                TextFont {
+                    font_size: 22.0,
+                    ..default()
+                },
+                TextColor(SPEAKER_COLOR),
+            ),
+            (
+                Text::new(info.description.clone()),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(BODY_COLOR),
+            ),
+        ],
+    )
+}
+
+/// First launch only, before the K post process gets going
+pub fn photosensitivity_warning_screen() -> impl Bundle {
+    (
+        BlocksPlayer,
+        Pickable {
+            should_block_lower: true,
+            is_hoverable: true,
+        },
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            left: Val::Px(0.0),
+            top: Val::Px(0.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(18.0),
+            padding: UiRect::all(Val::Px(32.0)),
+            ..default()
+        },
+        GlobalZIndex(1200),
+        BackgroundColor(BG),
+        children![
+            section_heading("PHOTOSENSITIVITY WARNING"),
+            (
+                Text::new(
+                    "This game uses flashing colours, spinning patterns and distorted images \
+                     that may trigger seizures for people with photosensitive epilepsy.\n\
+                     Safe mode slows these effects down and removes the flashing.",
+                ),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(BODY_COLOR),
+                TextLayout::new_with_justify(Justify::Center),
+                Node {
+                    max_width: Val::Px(620.0),
+                    ..default()
+                },
+            ),
+            (
+                Node {
+                    flex_direction: FlexDirection::Row,
+                    column_gap: Val::Px(10.0),
+                    ..default()
+                },
+                children![
+                    warning_button("enable safe mode", WarningChoice::SafeMode),
+                    warning_button("continue", WarningChoice::Continue),
+                ],
+            ),
+        ],
+    )
+}
+
+fn warning_button(label: impl Into<String>, choice: WarningChoice) -> impl Bundle {
+    (
+        choice,
+        Focusable,
+        Pickable::default(),
+        Node {
+            padding: UiRect::axes(Val::Px(12.0), Val::Px(6.0)),
+            border: UiRect::all(Val::Px(1.0)),
+            border_radius: BorderRadius::all(Val::Px(4.0)),
+            ..default()
+        },
+        BackgroundColor(BUTTON_BG),
+        BorderColor::all(PANEL_BORDER),
+        children![(
+            Text::new(label),
+            TextFont {
+                font_size: 16.0,
+                ..default()
+            },
+            TextColor(NAME_COLOR),
+        )],
+    )
+}
+
+fn spacer_x() -> impl Bundle {
+    Node {
+        flex_grow: 1.0,
+        ..default()
+    }
+}
+
 pub fn credits_screen(elapsed_secs: f32) -> impl Bundle {
     let mins = (elapsed_secs / 60.0).floor() as u32;
     let secs = (elapsed_secs % 60.0).floor() as u32;
@@ -123,6 +817,7 @@ pub fn credits_screen(elapsed_secs: f32) -> impl Bundle {
 
     (
         CreditsScreen,
+        BlocksPlayer,
         Node {
             width: Val::Percent(100.0),
             height: Val::Percent(100.0),
@@ -160,6 +855,8 @@ pub fn credits_screen(elapsed_secs: f32) -> impl Bundle {
 pub fn l(font: Handle<Font>, text: impl Into<String>) -> impl Bundle {
     (
         SoulsSceen,
+        BlocksPlayer,
+        Focusable,
         Pickable {
             is_hoverable: true,
             should_block_lower: true,
@@ -0,0 +1,312 @@
+//! Renders a fixed test card through the [`K`] post process and compares it against golden PNGs.
+//!
+//! `cargo test --features k-snapshots --test k_snapshot [-- --bless]`
+//!
+//! Every time in [`TIMES`] is rendered offscreen and read back, `--bless` writes the goldens
+//! instead of comparing. Mismatches are written to `target/k-snapshots/` for a look.
+//! Without a gpu adapter (software ones count) it skips and passes.
+//!
+//! The app steps a fixed frame time and waits whole frames, so a run doesn't depend on how
+//! fast the machine is, only on the shader compiling within [`WARMUP_FRAMES`].
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
+use bevy::{
+    app::ScheduleRunnerPlugin,
+    asset::RenderAssetUsages,
+    camera::{RenderTarget, ScalingMode},
+    core_pipeline::tonemapping::Tonemapping,
+    image::{CompressedImageFormats, ImageSampler, ImageType},
+    prelude::*,
+    render::{
+        gpu_readback::{Readback, ReadbackComplete},
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    },
+    time::TimeUpdateStrategy,
+    window::ExitCondition,
+    winit::WinitPlugin,
+};
+use stoned::{k::K, post};
+
+const GOLDENS: &str = "snapshots/k";
+const FAILURES: &str = "target/k-snapshots";
+/// Rows of 256 rgba pixels are already aligned for the texture copy
+const SIZE: u32 = 256;
+/// Calm, mid wave, first peak, deep in the hole
+const TIMES: &[f32] = &[0.0, 7.5, 15.7, 78.5];
+const FRAME: Duration = Duration::from_nanos(16_666_667);
+/// The shader compiles in the background, frames before that come out unprocessed
+const WARMUP_FRAMES: u32 = 180;
+/// Readbacks lag a few frames behind a change of `time`
+const SETTLE_FRAMES: u32 = 15;
+/// Mean difference per channel
+const MAX_MEAN_DIFF: f32 = 1.5;
+/// Share of pixels allowed to be off by more than [`PIXEL_TOLERANCE`] in any channel
+const MAX_BAD_PIXELS: f32 = 0.005;
+const PIXEL_TOLERANCE: u8 = 16;
+
+fn main() -> ExitCode {
+    let bless = std::env::args().any(|arg| arg == "--bless");
+
+    if !has_adapter() {
+        println!("no gpu adapter, skipping k snapshots");
+        return ExitCode::SUCCESS;
+    }
+
+    let exit = App::new()
+        .add_plugins((
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: ExitCondition::DontExit,
+                    ..default()
+                })
+                .disable::<WinitPlugin>(),
+            ScheduleRunnerPlugin::run_loop(FRAME),
+            // the whole chain, K's render graph edges depend on its neighbours
+            post::add_passes,
+        ))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+        .insert_resource(Snapshots {
+            bless,
+            index: 0,
+            frames: 0,
+            problems: Vec::new(),
+        })
+        .add_systems(Startup, setup)
+        .run();
+
+    match exit {
+        AppExit::Success => ExitCode::SUCCESS,
+        AppExit::Error(code) => ExitCode::from(code.get()),
+    }
+}
+
+fn has_adapter() -> bool {
+    let instance = wgpu::Instance::default();
+    bevy::tasks::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default())).is_ok()
+}
+
+#[derive(Resource)]
+struct Snapshots {
+    bless: bool,
+    /// into [`TIMES`]
+    index: usize,
+    /// readbacks since `time` was last changed
+    frames: u32,
+    problems: Vec<String>,
+}
+
+fn snapshot_k(time: f32) -> K {
+    K {
+        time,
+        spiral_time: time,
+        ..default()
+    }
+}
+
+fn setup(
+    mut cmd: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let size = Extent3d {
+        width: SIZE,
+        height: SIZE,
+        depth_or_array_layers: 1,
+    };
+    let mut target = Image::new_fill(
+        size,
+        TextureDimension::D2,
+        &[0; 4],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    target.texture_descriptor.usage |=
+        TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC | TextureUsages::TEXTURE_BINDING;
+    let target = images.add(target);
+
+    cmd.spawn((
+        Mesh3d(meshes.add(Rectangle::new(2.0, 2.0))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color_texture: Some(images.add(test_card(size))),
+            unlit: true,
+            ..default()
+        })),
+    ));
+    cmd.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(target.clone().into()),
+        Projection::from(OrthographicProjection {
+            scaling_mode: ScalingMode::Fixed {
+                width: 2.0,
+                height: 2.0,
+            },
+            ..OrthographicProjection::default_3d()
+        }),
+        Transform::from_xyz(0.0, 0.0, 1.0).looking_at(Vec3::ZERO, Vec3::Y),
+        Tonemapping::None,
+        Msaa::Off,
+        snapshot_k(TIMES[0]),
+    ));
+    cmd.spawn(Readback::texture(target)).observe(on_readback);
+}
+
+/// Gradients with a checkerboard on top, so both colour and distortion show up
+fn test_card(size: Extent3d) -> Image {
+    let mut data = Vec::with_capacity((size.width * size.height * 4) as usize);
+    for y in 0..size.height {
+        for x in 0..size.width {
+            let checker = ((x / 32) + (y / 32)).is_multiple_of(2);
+            let shade = if checker { 255 } else { 160 };
+            let r = (x * 255 / size.width) as u8;
+            let g = (y * 255 / size.height) as u8;
+            data.extend_from_slice(&[r, g, shade, 255]);
+        }
+    }
+    Image::new(
+        size,
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
+
+fn on_readback(
+    on: On<ReadbackComplete>,
+    mut snapshots: ResMut<Snapshots>,
+    mut k: Single<&mut K>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    let settle = if snapshots.index == 0 {
+        WARMUP_FRAMES
+    } else {
+        SETTLE_FRAMES
+    };
+    snapshots.frames += 1;
+    if snapshots.frames < settle {
+        return;
+    }
+
+    let time = TIMES[snapshots.index];
+    match check(time, &on.data, snapshots.bless) {
+        Ok(()) => println!("k at {time}: ok"),
+        Err(problem) => snapshots.problems.push(problem),
+    }
+
+    snapshots.index += 1;
+    if let Some(&next) = TIMES.get(snapshots.index) {
+        **k = snapshot_k(next);
+        snapshots.frames = 0;
+        return;
+    }
+
+    for problem in &snapshots.problems {
+        eprintln!("error: {problem}");
+    }
+    exit.write(if snapshots.problems.is_empty() {
+        AppExit::Success
+    } else {
+        AppExit::error()
+    });
+}
+
+fn file_name(time: f32) -> String {
+    format!("k_{time:.2}.png")
+}
+
+fn check(time: f32, actual: &[u8], bless: bool) -> Result<(), String> {
+    let golden = Path::new(GOLDENS).join(file_name(time));
+    if bless {
+        return save(actual, &golden);
+    }
+
+    let expected = std::fs::read(&golden)
+        .map_err(|e| format!("{}: {e}, run with --bless to create it", golden.display()))?;
+    let expected = Image::from_buffer(
+        &expected,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Default,
+        RenderAssetUsages::default(),
+    )
+    .map_err(|e| format!("{}: {e}", golden.display()))?
+    .convert(TextureFormat::Rgba8UnormSrgb)
+    .and_then(|image| image.data)
+    .ok_or_else(|| format!("{}: not an rgba image", golden.display()))?;
+
+    if let Err(problem) = compare(&expected, actual) {
+        let failure = PathBuf::from(FAILURES).join(file_name(time));
+        save(actual, &failure)?;
+        return Err(format!(
+            "k at {time}: {problem}, compare {} with {}",
+            failure.display(),
+            golden.display()
+        ));
+    }
+    Ok(())
+}
+
+fn compare(expected: &[u8], actual: &[u8]) -> Result<(), String> {
+    if expected.len() != actual.len() {
+        return Err(format!(
+            "size differs, {} bytes instead of {}",
+            actual.len(),
+            expected.len()
+        ));
+    }
+    let total: u64 = expected
+        .iter()
+        .zip(actual)
+        .map(|(a, b)| a.abs_diff(*b) as u64)
+        .sum();
+    let mean = total as f32 / expected.len() as f32;
+    let pixels = expected.len() / 4;
+    let bad = expected
+        .chunks_exact(4)
+        .zip(actual.chunks_exact(4))
+        .filter(|(a, b)| {
+            a.iter()
+                .zip(*b)
+                .any(|(a, b)| a.abs_diff(*b) > PIXEL_TOLERANCE)
+        })
+        .count();
+    let bad_share = bad as f32 / pixels as f32;
+
+    if mean > MAX_MEAN_DIFF || bad_share > MAX_BAD_PIXELS {
+        return Err(format!(
+            "mean difference {mean:.2}, {:.2}% of pixels off",
+            bad_share * 100.0
+        ));
+    }
+    Ok(())
+}
+
+fn save(data: &[u8], path: &Path) -> Result<(), String> {
+    let image = Image::new(
+        Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data.to_vec(),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("{}: {e}", parent.display()))?;
+    }
+    image
+        .try_into_dynamic()
+        .map_err(|e| format!("{}: {e}", path.display()))?
+        .save(path)
+        .map_err(|e| format!("{}: {e}", path.display()))
+}